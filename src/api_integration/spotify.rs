@@ -1,14 +1,50 @@
 use rspotify::{
-    model::{ PlaylistId, TrackId, PlayableItem, AlbumId, FullTrack, SimplifiedTrack },
+    model::{ PlaylistId, TrackId, EpisodeId, PlayableItem, AlbumId, FullTrack, FullEpisode, SimplifiedTrack },
     prelude::*,
     scopes, Credentials, OAuth, ClientCredsSpotify, ClientError
 };
+use serde::{Deserialize, Serialize};
+use std::{future::Future, time::Duration};
 use thiserror::Error as ThisError;
+use tokio::sync::mpsc;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+// the most items the api returns per page
+const PLAYLIST_PAGE_SIZE: u32 = 100;
+const ALBUM_PAGE_SIZE: u32 = 50;
+const BUFFERED_PAGES: usize = 4;
+
+// duration is kept to tell the right youtube upload apart from covers, live versions and loops
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SpotifyTrackData {
     pub title: String,
-    pub artists: Vec<String>
+    pub artists: Vec<String>,
+    pub duration: Duration
+}
+
+impl SpotifyTrackData {
+    pub fn query(&self) -> String {
+        format!("{} by {}", self.title, self.artists.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpotifyEpisodeData {
+    pub title: String,
+    pub show: String
+}
+
+// a single page of a playlist or an album, episodes can't be searched for on youtube so they're left out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpotifyPlaylistData {
+    pub tracks: Vec<SpotifyTrackData>,
+    pub skipped_episodes: usize
+}
+
+// the first page is fetched right away, the rest arrive through `rest` while they load
+#[derive(Debug)]
+pub struct SpotifyTrackPages {
+    pub first: SpotifyPlaylistData,
+    pub rest: mpsc::Receiver<SpotifyPlaylistData>
 }
 
 #[derive(Debug, ThisError)]
@@ -21,8 +57,6 @@ pub enum SpotifyError {
     Id(rspotify::model::IdError),
     #[error("")]
     EnvVarsMissing {vars: Vec<String>},
-    #[error("Episodes are not supported")]
-    EpisodesUnsupported,
     #[error("Provided playlist is private")]
     PlaylistPrivate,
 }
@@ -43,7 +77,8 @@ impl From<FullTrack> for SpotifyTrackData {
     fn from(value: FullTrack) -> Self {
         let title = value.name.to_owned();
         let artists = value.artists.iter().map(|artist| artist.name.clone()).collect::<Vec<String>>();
-        Self { title, artists }
+        let duration = value.duration.to_std().unwrap_or_default();
+        Self { title, artists, duration }
     }
 }
 
@@ -51,7 +86,14 @@ impl From<&FullTrack> for SpotifyTrackData {
     fn from(value: &FullTrack) -> Self {
         let title = value.name.to_owned();
         let artists = value.artists.iter().map(|artist| artist.name.clone()).collect::<Vec<String>>();
-        Self { title, artists }
+        let duration = value.duration.to_std().unwrap_or_default();
+        Self { title, artists, duration }
+    }
+}
+
+impl From<FullEpisode> for SpotifyEpisodeData {
+    fn from(value: FullEpisode) -> Self {
+        Self { title: value.name, show: value.show.name }
     }
 }
 
@@ -59,87 +101,131 @@ impl From<&SimplifiedTrack> for SpotifyTrackData {
     fn from(value: &SimplifiedTrack) -> Self {
         let title = value.name.to_owned();
         let artists = value.artists.iter().map(|artist| artist.name.clone()).collect::<Vec<String>>();
-        Self { title, artists }
+        let duration = value.duration.to_std().unwrap_or_default();
+        Self { title, artists, duration }
     }
 }
 
+#[derive(Clone)]
 pub struct SpotifyClient {
     client: ClientCredsSpotify
 }
 
 impl SpotifyClient {
-    pub fn new() -> Result<Self, SpotifyError> {
+    pub async fn new() -> Result<Self, SpotifyError> {
         let creds = Credentials::from_env().ok_or(SpotifyError::EnvVarsMissing { vars: vec!["RSPOTIFY_CLIENT_ID".to_string(), "RSPOTIFY_CLIENT_SECRET".to_string()] })?;
         OAuth::from_env(scopes!("playlist-read-private","playlist-read-collaborative","user-read-private","user-library-read")).ok_or(SpotifyError::EnvVarsMissing { vars: vec!["RSPOTIFY_REDIRECT_URI".to_string()] })?;
         let mut client = ClientCredsSpotify::new(creds);
-        client.request_token()?;
+        client.request_token().await?;
         client.config.token_refreshing = true;
         Ok(Self { client })
     }
 
-    pub fn track(&self, id: &str) -> Result<SpotifyTrackData, SpotifyError> {
+    pub async fn track(&self, id: &str) -> Result<SpotifyTrackData, SpotifyError> {
         let track_id = TrackId::from_id(id)?;
-        let track = self.client.track(track_id, None)?;
+        let track = self.client.track(track_id, None).await?;
 
         Ok(SpotifyTrackData::from(track))
     }
 
-    pub fn playlist(&self, id: &str) -> Result<Vec<SpotifyTrackData>, SpotifyError> {
-        let playlist_id = PlaylistId::from_id(id)?;
-        let playlist = match self.client.playlist(playlist_id, None, None) {
-            Ok(playlist) => playlist,
-            Err(err) => {
-                if let ClientError::Http(http_error) = &err {
-                    if let rspotify::http::HttpError::StatusCode(status_code) = http_error.as_ref() {
-                        if status_code.status() == 404 {
-                            return Err(SpotifyError::PlaylistPrivate);
-                        }
-                    }
-                }
-                return Err(err.into());
-            }
-        };
+    pub async fn episode(&self, id: &str) -> Result<SpotifyEpisodeData, SpotifyError> {
+        let episode_id = EpisodeId::from_id(id)?;
+        let episode = self.client.get_an_episode(episode_id, None).await?;
+
+        Ok(SpotifyEpisodeData::from(episode))
+    }
+
+    pub async fn playlist(&self, id: &str) -> Result<SpotifyTrackPages, SpotifyError> {
+        let playlist_id = PlaylistId::from_id(id)?.into_static();
+        let (first, has_next) = self.playlist_page(playlist_id.clone(), 0).await?;
+        if first.tracks.is_empty() && !has_next { return Err(SpotifyError::EmptyPlaylist); }
+
+        let client = self.clone();
+        let rest = load_remaining_pages(has_next, PLAYLIST_PAGE_SIZE, move |offset| {
+            let client = client.clone();
+            let playlist_id = playlist_id.clone();
+            async move { client.playlist_page(playlist_id, offset).await }
+        });
+        Ok(SpotifyTrackPages { first, rest })
+    }
+
+    pub async fn album(&self, id: &str) -> Result<SpotifyTrackPages, SpotifyError> {
+        let album_id = AlbumId::from_id(id)?.into_static();
+        let (first, has_next) = self.album_page(album_id.clone(), 0).await?;
+        if first.tracks.is_empty() && !has_next { return Err(SpotifyError::EmptyPlaylist); }
+
+        let client = self.clone();
+        let rest = load_remaining_pages(has_next, ALBUM_PAGE_SIZE, move |offset| {
+            let client = client.clone();
+            let album_id = album_id.clone();
+            async move { client.album_page(album_id, offset).await }
+        });
+        Ok(SpotifyTrackPages { first, rest })
+    }
+
+    // also returns whether there's a page after this one
+    async fn playlist_page(&self, playlist_id: PlaylistId<'static>, offset: u32) -> Result<(SpotifyPlaylistData, bool), SpotifyError> {
+        let page = self.client.playlist_items_manual(playlist_id, None, None, Some(PLAYLIST_PAGE_SIZE), Some(offset)).await.map_err(not_found_as_private)?;
         let mut tracks = vec![];
+        let mut skipped_episodes = 0;
 
-        for item in playlist.tracks.items.iter() {
+        for item in page.items.iter() {
             if let Some(playable_item) = &item.track {
                 match playable_item {
                     PlayableItem::Track(track) => {
                         tracks.push(SpotifyTrackData::from(track))
                     },
                     PlayableItem::Episode(_) => {
-                        return Err(SpotifyError::EpisodesUnsupported);
+                        skipped_episodes += 1;
                     }
                 }
             }
         }
-        
-        if tracks.is_empty() { return Err(SpotifyError::EmptyPlaylist.into()); }
-        Ok(tracks)
+        Ok((SpotifyPlaylistData { tracks, skipped_episodes }, page.next.is_some()))
     }
 
-    pub fn album(&self, id: &str) -> Result<Vec<SpotifyTrackData>, SpotifyError> {
-        let album_id = AlbumId::from_id(id)?;
-        let album = match self.client.album(album_id, None) {
-            Ok(album) => album,
-            Err(err) => {
-                if let ClientError::Http(http_error) = &err {
-                    if let rspotify::http::HttpError::StatusCode(status_code) = http_error.as_ref() {
-                        if status_code.status() == 404 {
-                            return Err(SpotifyError::PlaylistPrivate);
-                        }
-                    }
-                }
-                return Err(err.into());
-            }
-        };
-        let mut tracks = vec![];
+    async fn album_page(&self, album_id: AlbumId<'static>, offset: u32) -> Result<(SpotifyPlaylistData, bool), SpotifyError> {
+        let page = self.client.album_track_manual(album_id, None, Some(ALBUM_PAGE_SIZE), Some(offset)).await.map_err(not_found_as_private)?;
+        let tracks = page.items.iter().map(SpotifyTrackData::from).collect::<Vec<SpotifyTrackData>>();
+        Ok((SpotifyPlaylistData { tracks, skipped_episodes: 0 }, page.next.is_some()))
+    }
+}
 
-        for item in album.tracks.items.iter() {
-            tracks.push(SpotifyTrackData::from(item));
+// the api responds with 404 to playlists which aren't public
+fn not_found_as_private(err: ClientError) -> SpotifyError {
+    if let ClientError::Http(http_error) = &err {
+        if let rspotify::http::HttpError::StatusCode(status_code) = http_error.as_ref() {
+            if status_code.status() == 404 {
+                return SpotifyError::PlaylistPrivate;
+            }
         }
-
-        if tracks.is_empty() { return Err(SpotifyError::EmptyPlaylist.into()); }
-        Ok(tracks)
     }
-}
\ No newline at end of file
+    err.into()
+}
+
+// fetches the pages after the first one in the background, stops once nobody is waiting for them anymore
+fn load_remaining_pages<F, Fut>(has_next: bool, page_size: u32, fetch_page: F) -> mpsc::Receiver<SpotifyPlaylistData>
+where
+    F: Fn(u32) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(SpotifyPlaylistData, bool), SpotifyError>> + Send
+{
+    let (sender, receiver) = mpsc::channel(BUFFERED_PAGES);
+    if !has_next { return receiver; }
+
+    tokio::spawn(async move {
+        let mut offset = page_size;
+        loop {
+            match fetch_page(offset).await {
+                Ok((page, has_next)) => {
+                    if sender.send(page).await.is_err() || !has_next { break; }
+                    offset += page_size;
+                },
+                Err(err) => {
+                    log::error!("{:?}", err);
+                    break;
+                }
+            }
+        }
+    });
+    receiver
+}
@@ -3,8 +3,18 @@ use std::str::FromStr;
 use crate::{metadata::VideoMetadata, metadata::AudioSource};
 use thiserror::Error as ThisError;
 
+// the api doesn't return more items per request
+const MAX_RESULTS: usize = 50;
+pub const DEFAULT_PLAYLIST_LIMIT: usize = 500;
+
 pub struct YouTubeClient {
-    client: YouTube<HttpsConnector<HttpConnector>>
+    client: YouTube<HttpsConnector<HttpConnector>>,
+    playlist_limit: usize
+}
+
+pub struct YouTubePlaylistData {
+    pub videos: Vec<VideoMetadata>,
+    pub skipped: usize
 }
 
 #[derive(Debug, ThisError)]
@@ -50,8 +60,10 @@ impl YouTubeClient {
             client_x509_cert_url: Some(std::env::var("YOUTUBE_CLIENT_X509_CERT_URL").map_err(|_| YouTubeError::EnvVarsMissing { vars: vec!["YOUTUBE_CLIENT_X509_CERT_URL".to_owned()] })?)
         };
 
+        let playlist_limit = std::env::var("YOUTUBE_PLAYLIST_LIMIT").ok().and_then(|limit| limit.parse::<usize>().ok()).filter(|limit| *limit > 0).unwrap_or(DEFAULT_PLAYLIST_LIMIT);
+
         let youtube_auth = oauth2::ServiceAccountAuthenticator::builder(youtube_secret).build().await?;
-        Ok(Self { client: YouTube::new(Client::builder().build(HttpsConnectorBuilder::new().with_native_roots()?.https_or_http().enable_http1().enable_http2().build()), youtube_auth) })
+        Ok(Self { client: YouTube::new(Client::builder().build(HttpsConnectorBuilder::new().with_native_roots()?.https_or_http().enable_http1().enable_http2().build()), youtube_auth), playlist_limit })
     }
 
     pub async fn video(&self, id: &str) -> Result<VideoMetadata, YouTubeError> {
@@ -65,33 +77,70 @@ impl YouTubeClient {
         video_to_metadata(video)
     }
 
-    pub async fn playlist(&self, id: &str) -> Result<Vec<VideoMetadata>, YouTubeError> {
-        let items = self.client.playlist_items()
-            .list(&vec!["contentDetails".to_owned(), "snippet".to_owned()])
-            .playlist_id(id)
-            .max_results(50)
-            .doit().await?.1.items
-            .ok_or(YouTubeError::MissingValue { value: "playlist.items".to_owned() })?;
+    // pages through the whole playlist, entries before `offset` are left out and ones past the limit are counted as skipped
+    pub async fn playlist(&self, id: &str, offset: usize) -> Result<YouTubePlaylistData, YouTubeError> {
+        let mut video_ids = vec![];
+        let mut skipped = 0;
+        let mut position = 0;
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut request = self.client.playlist_items()
+                .list(&vec!["contentDetails".to_owned()])
+                .playlist_id(id)
+                .max_results(MAX_RESULTS as u32);
+            if let Some(page_token) = &page_token {
+                request = request.page_token(page_token);
+            }
+            let response = request.doit().await?.1;
+            let items = response.items.ok_or(YouTubeError::MissingValue { value: "playlist.items".to_owned() })?;
+            let total_results = response.page_info.and_then(|page_info| page_info.total_results).unwrap_or_default().max(0) as usize;
 
-        if items.is_empty() { return Err(YouTubeError::EmptyPlaylist.into()); }
+            for item in items {
+                position += 1;
+                if position <= offset { continue; }
+                match item.content_details.and_then(|content_details| content_details.video_id) {
+                    Some(video_id) => video_ids.push(video_id),
+                    None => skipped += 1
+                }
+                if video_ids.len() == self.playlist_limit { break; }
+            }
 
-        let mut video_id_vec = vec![];
-        for item in items {
-            let video_id = item
-                .content_details.ok_or(YouTubeError::MissingValue { value: "item.content_details".to_owned() })?
-                .video_id.ok_or(YouTubeError::MissingValue { value: "content_details.video_id".to_owned() })?;
-            video_id_vec.push(video_id);
+            if video_ids.len() == self.playlist_limit {
+                skipped += total_results.saturating_sub(position);
+                break;
+            }
+            page_token = response.next_page_token;
+            if page_token.is_none() { break; }
         }
 
-        let joined_video_ids = video_id_vec.join(",");
-        let items = self.client.videos()
-            .list(&vec!["contentDetails".to_owned(), "snippet".to_owned()])
-            .add_id(&joined_video_ids)
-            .doit().await?.1.items
-            .ok_or(YouTubeError::MissingValue { value: "video.items".to_owned() })?;
+        if video_ids.is_empty() { return Err(YouTubeError::EmptyPlaylist); }
+
+        let mut videos = vec![];
+        for ids in video_ids.chunks(MAX_RESULTS) {
+            let items = self.client.videos()
+                .list(&vec!["contentDetails".to_owned(), "snippet".to_owned()])
+                .add_id(&ids.join(","))
+                .doit().await?.1.items
+                .unwrap_or_default();
+
+            // private and deleted videos aren't returned at all
+            skipped += ids.len() - items.len();
+            let mut chunk = vec![];
+            for video in &items {
+                match video_to_metadata(video) {
+                    Ok(video_metadata) => chunk.push(video_metadata),
+                    Err(_) => skipped += 1
+                }
+            }
+            chunk.sort_by_key(|video_metadata| match &video_metadata.audio_source {
+                AudioSource::YouTube { video_id } => ids.iter().position(|id| id == video_id),
+                _ => None
+            });
+            videos.extend(chunk);
+        }
 
-        let metadata_vec = items.iter().map(|video| video_to_metadata(video)).collect::<Result<Vec<VideoMetadata>, YouTubeError>>()?;
-        Ok(metadata_vec)
+        if videos.is_empty() { return Err(YouTubeError::EmptyPlaylist); }
+        Ok(YouTubePlaylistData { videos, skipped })
     }
 }
 
@@ -0,0 +1,121 @@
+use std::{path::Path, time::Duration};
+
+use crate::{http_stream::create_seekable_http_stream, metadata::{AudioSource, VideoMetadata}};
+use percent_encoding::percent_decode_str;
+use songbird::input::{codecs::get_probe, AudioStream, AudioStreamError};
+use symphonia::core::{formats::FormatOptions, io::{MediaSource, MediaSourceStream}, meta::{MetadataOptions, MetadataRevision, StandardTagKey}, probe::Hint};
+use thiserror::Error as ThisError;
+use url::Url;
+
+pub const AUDIO_EXTENSIONS: [&str; 8] = ["mp3", "ogg", "oga", "opus", "flac", "m4a", "wav", "aac"];
+
+#[derive(Debug, ThisError)]
+pub enum ProbeError {
+    #[error("Couldn't open the audio file")]
+    Stream(AudioStreamError),
+    #[error("Unsupported audio format")]
+    Format,
+    #[error("{0}")]
+    Url(#[from] crate::url_guard::UrlGuardError)
+}
+
+fn extension(url: &Url) -> Option<String> {
+    let file_name = url.path_segments()?.last()?;
+    file_name.rsplit_once('.').map(|(_, extension)| extension.to_lowercase())
+}
+
+// only the extension is checked, the file itself gets probed once it's played
+pub fn is_audio_url(url: &Url) -> bool {
+    extension(url).is_some_and(|extension| AUDIO_EXTENSIONS.contains(&extension.as_str()))
+}
+
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| AUDIO_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+// fills the metadata of a direct link from its tags, the file name is used as the title if there are none
+pub async fn probe_url(client: reqwest::Client, url: String) -> Result<VideoMetadata, ProbeError> {
+    crate::url_guard::check_url(&url).await?;
+    let AudioStream { input, hint } = create_seekable_http_stream(client, url.clone()).await.map_err(ProbeError::Stream)?;
+    let parsed_url = Url::parse(&url).map_err(|_| ProbeError::Format)?;
+
+    let mut hint = hint.unwrap_or_default();
+    if let Some(extension) = extension(&parsed_url) {
+        hint.with_extension(&extension);
+    }
+
+    // probing reads from the stream synchronously
+    let tags = tokio::task::spawn_blocking(move || read_tags(input, hint)).await.map_err(|_| ProbeError::Format)??;
+    let title = tags.display_title().unwrap_or_else(|| file_name(&parsed_url));
+    Ok(VideoMetadata { title, duration: tags.duration, audio_source: AudioSource::Url { url } })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AudioTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub track_number: Option<u32>,
+    pub duration: Duration // zero if unknown
+}
+
+impl AudioTags {
+    pub fn display_title(&self) -> Option<String> {
+        let title = self.title.clone()?;
+        Some(match &self.artist {
+            Some(artist) => format!("{} - {}", artist, title),
+            None => title
+        })
+    }
+}
+
+// blocks while reading, the tags found first win
+pub fn read_tags(input: Box<dyn MediaSource>, hint: Hint) -> Result<AudioTags, ProbeError> {
+    let source = MediaSourceStream::new(input, Default::default());
+    let mut probed = get_probe().format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|_| ProbeError::Format)?;
+
+    // tags can be stored in front of the container (id3) or inside of it
+    let mut tags = AudioTags::default();
+    if let Some(revision) = probed.metadata.get().as_ref().and_then(|metadata| metadata.current()) {
+        fill_tags(&mut tags, revision);
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        fill_tags(&mut tags, revision);
+    }
+
+    // unknown for streams without a frame count, e.g. mp3s without a xing header
+    tags.duration = probed.format.default_track()
+        .and_then(|track| Some(track.codec_params.time_base?.calc_time(track.codec_params.n_frames?)))
+        .map(|time| Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac))
+        .unwrap_or_default();
+
+    Ok(tags)
+}
+
+fn fill_tags(tags: &mut AudioTags, revision: &MetadataRevision) {
+    for tag in revision.tags() {
+        let value = tag.value.to_string().trim().to_owned();
+        if value.is_empty() { continue; }
+        match tag.std_key {
+            Some(StandardTagKey::TrackTitle) if tags.title.is_none() => tags.title = Some(value),
+            Some(StandardTagKey::Artist) if tags.artist.is_none() => tags.artist = Some(value),
+            Some(StandardTagKey::Album) if tags.album.is_none() => tags.album = Some(value),
+            // stored as "3" or "3/12"
+            Some(StandardTagKey::TrackNumber) if tags.track_number.is_none() => tags.track_number = value.split('/').next().and_then(|number| number.trim().parse().ok()),
+            _ => ()
+        }
+    }
+}
+
+fn file_name(url: &Url) -> String {
+    let file_name = url.path_segments().and_then(|segments| segments.last()).unwrap_or_default();
+    let file_name = percent_decode_str(file_name).decode_utf8_lossy();
+    let file_name = file_name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(&file_name);
+    match file_name.is_empty() {
+        true => url.to_string(),
+        false => file_name.replace('_', " ")
+    }
+}
@@ -0,0 +1,522 @@
+use std::{collections::VecDeque, io::{Error as IoError, ErrorKind as IoErrorKind, Read, Result as IoResult, Seek, SeekFrom}, sync::{Arc, PoisonError, RwLock}, time::Duration};
+
+use songbird::{input::{codecs::{get_codec_registry, get_probe}, AudioStream, AudioStreamError, AuxMetadata, Compose, Input}, tracks::Track};
+use serde::{Deserialize, Serialize};
+use symphonia::core::{audio::{Channels, SampleBuffer}, codecs::{Decoder, DecoderOptions}, errors::Error as SymphoniaError, formats::{FormatOptions, FormatReader, SeekMode, SeekTo}, io::{MediaSource, MediaSourceStream}, meta::MetadataOptions, units::{Time, TimeBase}};
+
+// songbird's raw pcm format: a magic string followed by the sample rate and the channel count as LE u32s
+const RAW_MAGIC: &[u8; 8] = b"SbirdRaw";
+const RAW_HEADER_LEN: u64 = 16;
+const SAMPLE_SIZE: u64 = std::mem::size_of::<f32>() as u64;
+const MAX_CHANNELS: usize = 2; // the raw format is only read as mono or stereo
+const SURROUND_WEIGHT: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+const NORMALIZATION_WINDOW: Duration = Duration::from_secs(10);
+const LIVE_NORMALIZATION_WINDOW: Duration = Duration::from_secs(2); // live streams arrive in real time, a long preroll would delay playback
+const NORMALIZATION_BLOCK: Duration = Duration::from_millis(400);
+const TARGET_LOUDNESS: f32 = -16.0; // dBFS
+const SILENCE_GATE: f32 = -70.0; // dBFS
+const MAX_NORMALIZATION_GAIN: f32 = 4.0;
+
+const BASS_FREQUENCY: f32 = 100.0; // Hz
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AudioSettings {
+    pub normalization: bool,
+    pub filter: FilterPreset
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, poise::ChoiceParameter)]
+pub enum FilterPreset {
+    #[default]
+    #[name = "clear"]
+    #[name = "off"]
+    Off,
+    #[name = "bassboost"]
+    BassBoost,
+    #[name = "nightcore"]
+    Nightcore,
+    #[name = "vaporwave"]
+    Vaporwave
+}
+
+impl FilterPreset {
+    // playback rate, changes the pitch along with the speed
+    fn speed(&self) -> f64 {
+        match self {
+            Self::Nightcore => 1.25,
+            Self::Vaporwave => 0.8,
+            Self::Off | Self::BassBoost => 1.0
+        }
+    }
+
+    // gain of the low shelf in dB
+    fn bass_gain(&self) -> Option<f32> {
+        match self {
+            Self::BassBoost => Some(9.0),
+            Self::Vaporwave => Some(4.0),
+            Self::Off | Self::Nightcore => None
+        }
+    }
+}
+
+// read by processed streams while they play, so changes apply to the current track as well
+pub type SharedAudioSettings = Arc<RwLock<AudioSettings>>;
+
+// makes the track decode through the guild's audio processing
+pub fn process_track(mut track: Track, settings: &SharedAudioSettings) -> Track {
+    track.input = match track.input {
+        Input::Lazy(inner) => Input::Lazy(Box::new(ProcessedComposer { inner, settings: settings.clone() })),
+        input => input
+    };
+    track
+}
+
+pub struct ProcessedComposer {
+    inner: Box<dyn Compose>,
+    settings: SharedAudioSettings
+}
+
+// every stream gets processed so settings can be changed while a track is playing
+fn process_stream(stream: AudioStream<Box<dyn MediaSource>>, settings: SharedAudioSettings) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+    let source = ProcessedSource::new(stream, settings)?;
+    Ok(AudioStream { input: Box::new(source) as Box<dyn MediaSource>, hint: None })
+}
+
+#[serenity::async_trait]
+impl Compose for ProcessedComposer {
+    fn create(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        let stream = self.inner.create()?;
+        process_stream(stream, self.settings.clone())
+    }
+
+    async fn create_async(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        let stream = self.inner.create_async().await?;
+        let settings = self.settings.clone();
+        // decoding reads from the stream in a blocking manner
+        tokio::task::spawn_blocking(move || process_stream(stream, settings)).await
+            .map_err(|err| AudioStreamError::Fail(err.into()))?
+    }
+
+    async fn aux_metadata(&mut self) -> Result<AuxMetadata, AudioStreamError> {
+        self.inner.aux_metadata().await
+    }
+
+    fn should_create_async(&self) -> bool {
+        self.inner.should_create_async()
+    }
+}
+
+// decodes the original stream and hands processed samples to songbird as raw pcm
+pub struct ProcessedSource {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    time_base: Option<TimeBase>,
+    sample_rate: u32,
+    channels: usize,
+    n_frames: Option<u64>,
+    seekable: bool,
+    settings: SharedAudioSettings,
+    gain: f32,
+    bass_filter: Option<(FilterPreset, Vec<Biquad>)>,
+    resampler: Resampler,
+    header: [u8; RAW_HEADER_LEN as usize],
+    decoded: VecDeque<Vec<f32>>,
+    pending: Vec<u8>,
+    pending_offset: usize,
+    position: u64,
+    skip_frames: u64
+}
+
+impl ProcessedSource {
+    pub fn new(stream: AudioStream<Box<dyn MediaSource>>, settings: SharedAudioSettings) -> Result<Self, AudioStreamError> {
+        let seekable = stream.input.is_seekable();
+        let hint = stream.hint.unwrap_or_default();
+        let source = MediaSourceStream::new(stream.input, Default::default());
+        let probed = get_probe().format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+            .map_err(|err| AudioStreamError::Fail(err.into()))?;
+
+        let format = probed.format;
+        let track = format.default_track().ok_or(AudioStreamError::Fail("no audio track found".into()))?;
+        let decoder = get_codec_registry().make(&track.codec_params, &DecoderOptions::default())
+            .map_err(|err| AudioStreamError::Fail(err.into()))?;
+        let (track_id, time_base, n_frames) = (track.id, track.codec_params.time_base, track.codec_params.n_frames);
+        let sample_rate = track.codec_params.sample_rate.unwrap_or(48_000);
+        let channels = track.codec_params.channels.map(|channels| channels.count()).unwrap_or(2).min(MAX_CHANNELS);
+
+        let mut source = Self {
+            format,
+            decoder,
+            track_id,
+            time_base,
+            sample_rate,
+            channels,
+            n_frames,
+            seekable,
+            settings,
+            gain: 1.0,
+            bass_filter: None,
+            resampler: Resampler::default(),
+            header: [0; RAW_HEADER_LEN as usize],
+            decoded: VecDeque::new(),
+            pending: vec![],
+            pending_offset: 0,
+            position: 0,
+            skip_frames: 0
+        };
+
+        // the loudness is only measured for tracks which start with normalization enabled, enabling it mid track leaves that track as it is
+        let normalization = source.settings.read().unwrap_or_else(PoisonError::into_inner).normalization;
+        if normalization {
+            source.measure_gain()?;
+        }
+
+        source.header[..8].copy_from_slice(RAW_MAGIC);
+        source.header[8..12].copy_from_slice(&source.sample_rate.to_le_bytes());
+        source.header[12..].copy_from_slice(&(source.channels as u32).to_le_bytes());
+        Ok(source)
+    }
+
+    // decodes the first seconds up front to estimate the track's loudness
+    fn measure_gain(&mut self) -> Result<(), AudioStreamError> {
+        let window = match self.n_frames.is_none() && !self.seekable {
+            true => LIVE_NORMALIZATION_WINDOW,
+            false => NORMALIZATION_WINDOW
+        };
+        let window_samples = (window.as_secs_f32() * self.sample_rate as f32) as usize * self.channels;
+        let mut prerolled = vec![];
+        while prerolled.len() < window_samples {
+            match self.decode_packet().map_err(|err| AudioStreamError::Fail(err.into()))? {
+                Some(samples) => {
+                    prerolled.extend_from_slice(&samples);
+                    self.decoded.push_back(samples);
+                },
+                None => break
+            }
+        }
+        if let Some(loudness) = measure_loudness(&prerolled, self.sample_rate, self.channels) {
+            self.gain = 10f32.powf((TARGET_LOUDNESS - loudness) / 20.0).min(MAX_NORMALIZATION_GAIN);
+        }
+        Ok(())
+    }
+
+    // decodes the next packet of the selected track into interleaved samples, None at the end of the stream
+    fn decode_packet(&mut self) -> IoResult<Option<Vec<f32>>> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(err)) if err.kind() == IoErrorKind::UnexpectedEof => return Ok(None),
+                Err(SymphoniaError::ResetRequired) => return Ok(None),
+                Err(err) => return Err(IoError::new(IoErrorKind::Other, err))
+            };
+            if packet.track_id() != self.track_id { continue; }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(SymphoniaError::DecodeError(_)) => continue, // corrupted packets are skipped
+                Err(err) => return Err(IoError::new(IoErrorKind::Other, err))
+            };
+            if decoded.frames() == 0 { continue; }
+
+            let spec = *decoded.spec();
+            let mut sample_buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+            sample_buffer.copy_interleaved_ref(decoded);
+            // surround files are played as stereo
+            if spec.channels.count() > MAX_CHANNELS {
+                return Ok(Some(downmix(sample_buffer.samples(), &downmix_weights(spec.channels))));
+            }
+            return Ok(Some(sample_buffer.samples().to_vec()));
+        }
+    }
+
+    // refills the pending bytes, false at the end of the stream
+    fn fill_pending(&mut self) -> IoResult<bool> {
+        loop {
+            let mut samples = match self.decoded.pop_front() {
+                Some(samples) => samples,
+                None => match self.decode_packet()? {
+                    Some(samples) => samples,
+                    None => return Ok(false)
+                }
+            };
+
+            // accurate seeks may land before the requested frame
+            if self.skip_frames > 0 {
+                let skip = (self.skip_frames as usize * self.channels).min(samples.len());
+                samples.drain(..skip);
+                self.skip_frames -= (skip / self.channels) as u64;
+            }
+            if samples.is_empty() { continue; }
+
+            let samples = self.process(samples);
+            if samples.is_empty() { continue; }
+            self.pending.clear();
+            self.pending_offset = 0;
+            self.pending.extend(samples.iter().flat_map(|sample| sample.to_le_bytes()));
+            return Ok(true);
+        }
+    }
+
+    fn process(&mut self, samples: Vec<f32>) -> Vec<f32> {
+        // a panic while the settings were locked leaves them intact, so playback keeps using them
+        let settings = self.settings.read().unwrap_or_else(PoisonError::into_inner).clone();
+        let mut samples = self.resampler.process(samples, self.channels, settings.filter.speed());
+
+        match settings.filter.bass_gain() {
+            Some(gain) => {
+                // the filters are recreated on preset changes, their state doesn't carry over between presets
+                if !matches!(&self.bass_filter, Some((preset, _)) if *preset == settings.filter) {
+                    let filters = (0..self.channels).map(|_| Biquad::low_shelf(BASS_FREQUENCY, gain, self.sample_rate)).collect();
+                    self.bass_filter = Some((settings.filter, filters));
+                }
+                if let Some((_, filters)) = &mut self.bass_filter {
+                    for frame in samples.chunks_mut(self.channels) {
+                        for (sample, filter) in frame.iter_mut().zip(filters.iter_mut()) {
+                            *sample = filter.process(*sample);
+                        }
+                    }
+                }
+            },
+            None => self.bass_filter = None
+        }
+
+        let gain = if settings.normalization { self.gain } else { 1.0 };
+        for sample in samples.iter_mut() {
+            *sample = (*sample * gain).clamp(-1.0, 1.0);
+        }
+        samples
+    }
+
+    fn speed(&self) -> f64 {
+        self.settings.read().unwrap_or_else(PoisonError::into_inner).filter.speed()
+    }
+
+    fn frame_size(&self) -> u64 {
+        SAMPLE_SIZE * self.channels as u64
+    }
+
+    // converts a timestamp of the track into a frame index
+    fn ts_to_frames(&self, ts: u64) -> u64 {
+        match self.time_base {
+            Some(time_base) => {
+                let time = time_base.calc_time(ts);
+                ((time.seconds as f64 + time.frac) * self.sample_rate as f64) as u64
+            },
+            None => ts
+        }
+    }
+}
+
+impl Read for ProcessedSource {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        if self.position < RAW_HEADER_LEN {
+            let header = &self.header[self.position as usize..];
+            let amount = header.len().min(buf.len());
+            buf[..amount].copy_from_slice(&header[..amount]);
+            self.position += amount as u64;
+            return Ok(amount);
+        }
+
+        while self.pending_offset >= self.pending.len() {
+            if !self.fill_pending()? { return Ok(0); }
+        }
+
+        let amount = (self.pending.len() - self.pending_offset).min(buf.len());
+        buf[..amount].copy_from_slice(&self.pending[self.pending_offset..self.pending_offset + amount]);
+        self.pending_offset += amount;
+        self.position += amount as u64;
+        Ok(amount)
+    }
+}
+
+impl Seek for ProcessedSource {
+    fn seek(&mut self, position: SeekFrom) -> IoResult<u64> {
+        let target = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.byte_len().ok_or(IoError::from(IoErrorKind::Unsupported))?.checked_add_signed(delta)
+        }.ok_or(IoError::from(IoErrorKind::InvalidInput))?;
+        if target == self.position { return Ok(target); }
+        if !self.seekable { return Err(IoErrorKind::Unsupported.into()); }
+
+        // songbird counts played frames, the speed of the current filter maps them onto the track
+        let frame = target.saturating_sub(RAW_HEADER_LEN) / self.frame_size();
+        let time = Time::from(frame as f64 * self.speed() / self.sample_rate as f64);
+        let seeked_to = self.format.seek(SeekMode::Accurate, SeekTo::Time { time, track_id: Some(self.track_id) })
+            .map_err(|err| IoError::new(IoErrorKind::Other, err))?;
+        self.decoder.reset();
+
+        self.decoded.clear();
+        self.pending.clear();
+        self.pending_offset = 0;
+        self.resampler = Resampler::default();
+        self.bass_filter = None;
+        self.skip_frames = self.ts_to_frames(seeked_to.required_ts).saturating_sub(self.ts_to_frames(seeked_to.actual_ts));
+        self.position = if target < RAW_HEADER_LEN { target } else { RAW_HEADER_LEN + frame * self.frame_size() };
+        Ok(self.position)
+    }
+}
+
+impl MediaSource for ProcessedSource {
+    fn is_seekable(&self) -> bool {
+        self.seekable
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.n_frames.map(|n_frames| RAW_HEADER_LEN + (n_frames as f64 / self.speed()) as u64 * self.frame_size())
+    }
+}
+
+// gated mean square loudness in dBFS over short blocks, loosely following EBU R128 without k-weighting
+// how much of every input channel goes into the left and the right output channel
+fn downmix_weights(channels: Channels) -> Vec<(f32, f32)> {
+    let left = Channels::FRONT_LEFT_CENTRE | Channels::REAR_LEFT | Channels::SIDE_LEFT | Channels::REAR_LEFT_CENTRE | Channels::FRONT_LEFT_WIDE
+        | Channels::FRONT_LEFT_HIGH | Channels::TOP_FRONT_LEFT | Channels::TOP_REAR_LEFT;
+    let right = Channels::FRONT_RIGHT_CENTRE | Channels::REAR_RIGHT | Channels::SIDE_RIGHT | Channels::REAR_RIGHT_CENTRE | Channels::FRONT_RIGHT_WIDE
+        | Channels::FRONT_RIGHT_HIGH | Channels::TOP_FRONT_RIGHT | Channels::TOP_REAR_RIGHT;
+    channels.iter().map(|channel| {
+        if channel == Channels::FRONT_LEFT { (1.0, 0.0) }
+        else if channel == Channels::FRONT_RIGHT { (0.0, 1.0) }
+        else if channel == Channels::LFE1 || channel == Channels::LFE2 { (0.0, 0.0) } // left out like most downmixes do
+        else if left.contains(channel) { (SURROUND_WEIGHT, 0.0) }
+        else if right.contains(channel) { (0.0, SURROUND_WEIGHT) }
+        else { (SURROUND_WEIGHT, SURROUND_WEIGHT) } // centre channels go to both sides
+    }).collect()
+}
+
+// mixes interleaved frames down to stereo, scaled so a frame at full volume in every channel doesn't clip
+fn downmix(samples: &[f32], weights: &[(f32, f32)]) -> Vec<f32> {
+    if weights.is_empty() { return vec![]; }
+    let (left_total, right_total) = weights.iter().fold((0.0, 0.0), |(left, right), (left_weight, right_weight)| (left + left_weight, right + right_weight));
+    let scale = 1.0 / f32::max(left_total, right_total).max(1.0);
+    samples.chunks_exact(weights.len()).flat_map(|frame| {
+        let (left, right) = frame.iter().zip(weights).fold((0.0, 0.0), |(left, right), (sample, (left_weight, right_weight))| (left + sample * left_weight, right + sample * right_weight));
+        [left * scale, right * scale]
+    }).collect()
+}
+
+fn measure_loudness(samples: &[f32], sample_rate: u32, channels: usize) -> Option<f32> {
+    let block_len = (NORMALIZATION_BLOCK.as_secs_f32() * sample_rate as f32) as usize * channels;
+    if block_len == 0 { return None; }
+
+    let gated_powers = samples
+        .chunks(block_len)
+        .map(|block| block.iter().map(|sample| sample * sample).sum::<f32>() / block.len() as f32)
+        .filter(|power| 10.0 * power.log10() > SILENCE_GATE)
+        .collect::<Vec<f32>>();
+    if gated_powers.is_empty() { return None; }
+
+    let mean_power = gated_powers.iter().sum::<f32>() / gated_powers.len() as f32;
+    Some(10.0 * mean_power.log10())
+}
+
+// resamples by linear interpolation, the last frame of a chunk is kept to interpolate into the next one
+#[derive(Debug, Default)]
+struct Resampler {
+    last_frame: Vec<f32>,
+    position: f64
+}
+
+impl Resampler {
+    fn process(&mut self, samples: Vec<f32>, channels: usize, speed: f64) -> Vec<f32> {
+        if speed == 1.0 {
+            *self = Self::default();
+            return samples;
+        }
+
+        let mut frames = std::mem::take(&mut self.last_frame);
+        frames.extend_from_slice(&samples);
+        let frame_count = frames.len() / channels;
+        if frame_count < 2 {
+            self.last_frame = frames;
+            return vec![];
+        }
+
+        let mut output = Vec::with_capacity((frame_count as f64 / speed) as usize * channels + channels);
+        while self.position + 1.0 < frame_count as f64 {
+            let index = self.position as usize;
+            let fraction = (self.position - index as f64) as f32;
+            for channel in 0..channels {
+                let current = frames[index * channels + channel];
+                let next = frames[(index + 1) * channels + channel];
+                output.push(current + (next - current) * fraction);
+            }
+            self.position += speed;
+        }
+
+        self.position -= (frame_count - 1) as f64;
+        self.last_frame = frames[(frame_count - 1) * channels..].to_vec();
+        output
+    }
+}
+
+// second order iir filter, coefficients from the audio eq cookbook
+#[derive(Debug)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32
+}
+
+impl Biquad {
+    fn low_shelf(frequency: f32, gain: f32, sample_rate: u32) -> Self {
+        let a = 10f32.powf(gain / 40.0);
+        let w0 = 2.0 * std::f32::consts::PI * frequency / sample_rate as f32;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / 2.0 * std::f32::consts::SQRT_2; // shelf slope of 1
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let a0 = (a + 1.0) + (a - 1.0) * cos + sqrt_a_alpha;
+        Self {
+            b0: a * ((a + 1.0) - (a - 1.0) * cos + sqrt_a_alpha) / a0,
+            b1: 2.0 * a * ((a - 1.0) - (a + 1.0) * cos) / a0,
+            b2: a * ((a + 1.0) - (a - 1.0) * cos - sqrt_a_alpha) / a0,
+            a1: -2.0 * ((a - 1.0) + (a + 1.0) * cos) / a0,
+            a2: ((a + 1.0) + (a - 1.0) * cos - sqrt_a_alpha) / a0,
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0
+        }
+    }
+
+    fn process(&mut self, sample: f32) -> f32 {
+        let output = self.b0 * sample + self.b1 * self.x1 + self.b2 * self.x2 - self.a1 * self.y1 - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = sample;
+        self.y2 = self.y1;
+        self.y1 = output;
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downmixes_surround_to_stereo() {
+        // 5.1 in symphonia's order: front left, front right, centre, lfe, rear left, rear right
+        let layout = Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE | Channels::LFE1 | Channels::REAR_LEFT | Channels::REAR_RIGHT;
+        let weights = downmix_weights(layout);
+        assert_eq!(weights.len(), 6);
+
+        let frames = [
+            1.0, 0.0, 0.0, 0.0, 0.0, 0.0, // only the front left speaker
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, // only the subwoofer
+            1.0, 1.0, 1.0, 1.0, 1.0, 1.0 // every speaker at full volume
+        ];
+        let stereo = downmix(&frames, &weights);
+        assert_eq!(stereo.len(), 6);
+        assert!(stereo[0] > 0.0 && stereo[1] == 0.0);
+        assert_eq!((stereo[2], stereo[3]), (0.0, 0.0));
+        assert!((stereo[4] - 1.0).abs() < 1e-6 && (stereo[5] - 1.0).abs() < 1e-6);
+    }
+}
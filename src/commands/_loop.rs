@@ -1,24 +1,96 @@
-use crate::data::Context;
-use songbird::tracks::LoopState;
+use std::sync::Arc;
 
-use crate::commands::{error::VoiceError, utils::same_voice_channel, error::CommandError};
+use crate::{data::Context, audio_processing::process_track, guild_state::{GuildStates, LoopMode}, metadata::rebuild_track};
+use poise::async_trait;
+use songbird::{Call, EventContext, tracks::{PlayMode, TrackHandle}};
+use tokio::sync::Mutex;
 
-// loops the first track
+use crate::commands::{error::VoiceError, utils::{same_voice_channel, send_timed_reply}, error::CommandError};
+
+// sets the loop mode, without arguments cycles between off, track and queue
 #[poise::command(slash_command, prefix_command, guild_only, rename = "loop")]
-pub async fn _loop(ctx: Context<'_>) -> Result<(), CommandError> {
+pub async fn _loop(ctx: Context<'_>, mode: Option<LoopMode>, count: Option<usize>) -> Result<(), CommandError> {
     let guild = ctx.guild().unwrap().clone();
     let manager = songbird::get(&ctx.serenity_context()).await.ok_or(CommandError::Voice(VoiceError::NoManager))?;
-    
+
     if let Some(handler) = manager.get(guild.id) {
         if !same_voice_channel(&guild, &ctx.author().id, handler.clone()).await { return Ok(()); }
-        
-        let Some(current_track) = handler.lock().await.queue().current() else { return Ok(()); };
-        let info = current_track.get_info().await?;
-        if info.loops == LoopState::Infinite {
-            let _ = current_track.disable_loop();
-        } else {
-           let _ =  current_track.enable_loop();
+
+        let guild_states = &ctx.data().guild_states;
+        let mode = match mode {
+            Some(mode) => mode,
+            None => guild_states.with(guild.id.get(), |state| state.loop_mode).await.next()
+        };
+        let current_track = handler.lock().await.queue().current();
+        set_loop_mode(guild_states, guild.id.get(), current_track.clone(), mode, count).await?;
+
+        let loops = match current_track {
+            Some(current_track) => current_track.get_info().await.ok().map(|info| info.loops),
+            None => None
+        };
+        let mode = guild_states.with(guild.id.get(), |state| state.loop_mode).await;
+        let _ = send_timed_reply(&ctx, mode.describe(loops), None).await;
+    }
+    Ok(())
+}
+
+// track looping is applied to the current track and resets once that track ends
+pub async fn set_loop_mode(guild_states: &GuildStates, guild_id: u64, current_track: Option<TrackHandle>, mode: LoopMode, count: Option<usize>) -> Result<(), CommandError> {
+    match mode {
+        LoopMode::Track => {
+            let Some(current_track) = current_track else { return Ok(()); };
+            match count {
+                Some(count) => current_track.loop_for(count)?,
+                None => current_track.enable_loop()?
+            }
+            current_track.add_event(songbird::Event::Track(songbird::TrackEvent::End), LoopResetEventHandler { guild_states: guild_states.clone(), guild_id })?;
+        },
+        LoopMode::Off | LoopMode::Queue => {
+            if let Some(current_track) = current_track {
+                let _ = current_track.disable_loop();
+            }
         }
     }
+    guild_states.with(guild_id, |state| state.loop_mode = mode).await;
     Ok(())
-}
\ No newline at end of file
+}
+
+struct LoopResetEventHandler {
+    guild_states: GuildStates,
+    guild_id: u64
+}
+
+#[async_trait]
+impl songbird::events::EventHandler for LoopResetEventHandler {
+    async fn act(&self, _ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+        self.guild_states.with(self.guild_id, |state| {
+            if state.loop_mode == LoopMode::Track { state.loop_mode = LoopMode::Off; }
+        }).await;
+        None
+    }
+}
+
+// re-enqueues finished tracks while the queue is looping
+pub struct QueueLoopEventHandler {
+    pub handler: Arc<Mutex<Call>>,
+    pub guild_states: GuildStates,
+    pub guild_id: u64,
+    pub client: reqwest::Client
+}
+
+#[async_trait]
+impl songbird::events::EventHandler for QueueLoopEventHandler {
+    async fn act(&self, ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+        let EventContext::Track(slice) = ctx else { return None; };
+        let (loop_mode, audio_settings) = self.guild_states.with(self.guild_id, |state| (state.loop_mode, state.settings.audio_settings.clone())).await;
+        if loop_mode != LoopMode::Queue { return None; }
+
+        for (track_state, track_handle) in slice.iter() {
+            // stopped tracks were skipped or removed by hand
+            if !matches!(track_state.playing, PlayMode::End) { continue; }
+            let Some(track) = rebuild_track(track_handle, self.client.clone()).await else { continue; };
+            self.handler.lock().await.enqueue(process_track(track, &audio_settings)).await;
+        }
+        None
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{data::Context, metadata::LazyMetadata};
+
+use crate::commands::{ error::{VoiceError, CommandError}, utils::{same_voice_channel, send_timed_reply} };
+
+// moves a track to a different position in the queue, numbered as in the queue command
+#[poise::command(slash_command, prefix_command, guild_only, rename = "move", aliases("mv"))]
+pub async fn _move(ctx: Context<'_>, from: usize, to: usize) -> Result<(), CommandError> {
+    let guild = ctx.guild().unwrap().clone();
+    let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
+
+    if from == 0 || to == 0 { return Err(CommandError::InvalidIndex); }
+
+    if let Some(handler) = manager.get(guild.id) {
+        if !same_voice_channel(&guild, &ctx.author().id, handler.clone()).await { return Ok(()); }
+
+        let (track_handle, position) = handler.lock().await.queue().modify_queue(|queue| {
+            if from >= queue.len() { return None; }
+            let track = queue.remove(from)?;
+            let track_handle = (*track).clone();
+            let position = to.min(queue.len());
+            queue.insert(position, track);
+            Some((track_handle, position))
+        }).ok_or(CommandError::InvalidIndex)?;
+
+        // tracks which haven't been looked up yet only have their query
+        let title = match track_handle.read_lazy_metadata().await {
+            Some(track_metadata) => track_metadata.video_metadata.display_title(),
+            None => track_handle.read_query().await.unwrap_or_default()
+        };
+        let _ = send_timed_reply(&ctx, format!("Moved `{}` to position {}", title, position), None).await;
+    }
+    Ok(())
+}
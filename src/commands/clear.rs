@@ -0,0 +1,23 @@
+use crate::data::Context;
+
+use crate::commands::{ error::{VoiceError, CommandError}, utils::{same_voice_channel, send_timed_reply} };
+
+// clears the queue without stopping the current track
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn clear(ctx: Context<'_>) -> Result<(), CommandError> {
+    let guild = ctx.guild().unwrap().clone();
+    let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
+
+    if let Some(handler) = manager.get(guild.id) {
+        if !same_voice_channel(&guild, &ctx.author().id, handler.clone()).await { return Ok(()); }
+
+        let removed = handler.lock().await.queue().modify_queue(|queue| {
+            queue.drain(1.min(queue.len())..).collect::<Vec<_>>()
+        });
+        for track in removed.iter() {
+            let _ = track.stop();
+        }
+        let _ = send_timed_reply(&ctx, format!("Removed {} tracks", removed.len()), None).await;
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use crate::{data::Context, permissions::PermissionLevel};
+use poise::{ChoiceParameter, serenity_prelude::{GuildChannel, Role}};
+
+use crate::commands::{ error::CommandError, utils::send_timed_reply };
+
+const MAX_PREFIX_LENGTH: usize = 5;
+
+// per server configuration
+#[poise::command(slash_command, prefix_command, guild_only, subcommands("show", "prefix", "afktimeout", "announcements", "cleanup", "permissions", "dj"), subcommand_required)]
+pub async fn config(_ctx: Context<'_>) -> Result<(), CommandError> {
+    Ok(())
+}
+
+// shows the current configuration
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn show(ctx: Context<'_>) -> Result<(), CommandError> {
+    let settings = ctx.data().guild_states.with(ctx.guild_id().unwrap().get(), |state| state.settings.clone()).await;
+    let announcement_channel = match settings.announcement_channel {
+        Some(channel_id) => format!("<#{}>", channel_id),
+        None => "*channel of the command*".to_owned()
+    };
+
+    let description = format!(
+        "Prefix: `{}`\nAFK timeout: {}s\nAnnouncements: {}\nCleanup delays: {}s replies, {}s announcements",
+        settings.prefix, settings.afk_timeout_secs, announcement_channel, settings.reply_cleanup_secs, settings.announcement_cleanup_secs
+    );
+    let _ = send_timed_reply(&ctx, description, Some(std::time::Duration::from_secs(30))).await;
+    Ok(())
+}
+
+// sets the prefix of text commands
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn prefix(ctx: Context<'_>, prefix: String) -> Result<(), CommandError> {
+    let prefix = prefix.trim().to_owned();
+    if prefix.is_empty() || prefix.chars().count() > MAX_PREFIX_LENGTH || prefix.contains(char::is_whitespace) { return Err(CommandError::InvalidPrefix); }
+
+    ctx.data().guild_states.update_settings(ctx.guild_id().unwrap().get(), |settings| settings.prefix = prefix.clone()).await;
+    let _ = send_timed_reply(&ctx, format!("Prefix set to `{}`", prefix), None).await;
+    Ok(())
+}
+
+// sets how long the bot waits in an empty voice channel before leaving
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn afktimeout(ctx: Context<'_>, #[min = 0] #[max = 3600] seconds: u64) -> Result<(), CommandError> {
+    let seconds = seconds.min(3600);
+    ctx.data().guild_states.update_settings(ctx.guild_id().unwrap().get(), |settings| settings.afk_timeout_secs = seconds).await;
+    let _ = send_timed_reply(&ctx, format!("AFK timeout set to {}s", seconds), None).await;
+    Ok(())
+}
+
+// sets the channel for "now playing" messages, without arguments they go where the bot was summoned
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn announcements(ctx: Context<'_>, channel: Option<GuildChannel>) -> Result<(), CommandError> {
+    let channel_id = channel.map(|channel| channel.id.get());
+    ctx.data().guild_states.update_settings(ctx.guild_id().unwrap().get(), |settings| settings.announcement_channel = channel_id).await;
+
+    let message = match channel_id {
+        Some(channel_id) => format!("Announcements will be sent to <#{}>", channel_id),
+        None => "Announcements will be sent to the channel of the command".to_owned()
+    };
+    let _ = send_timed_reply(&ctx, message, None).await;
+    Ok(())
+}
+
+// sets how many seconds replies and announcements stay before getting deleted
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn cleanup(ctx: Context<'_>, #[min = 1] #[max = 600] replies: Option<u64>, #[min = 1] #[max = 600] announcements: Option<u64>) -> Result<(), CommandError> {
+    let (replies, announcements) = ctx.data().guild_states.update_settings(ctx.guild_id().unwrap().get(), |settings| {
+        if let Some(replies) = replies { settings.reply_cleanup_secs = replies.clamp(1, 600); }
+        if let Some(announcements) = announcements { settings.announcement_cleanup_secs = announcements.clamp(1, 600); }
+        (settings.reply_cleanup_secs, settings.announcement_cleanup_secs)
+    }).await;
+    let _ = send_timed_reply(&ctx, format!("Cleanup delays: {}s replies, {}s announcements", replies, announcements), None).await;
+    Ok(())
+}
+
+// shows the permission levels, or sets the level a command requires
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn permissions(ctx: Context<'_>, command: Option<String>, level: Option<PermissionLevel>) -> Result<(), CommandError> {
+    let guild_id = ctx.guild_id().unwrap().get();
+    let guild_states = &ctx.data().guild_states;
+
+    let Some(command) = command.map(|command| command.to_lowercase()) else {
+        let (mut permissions, dj_role) = guild_states.with(guild_id, |state| (state.settings.permissions.clone().into_iter().collect::<Vec<_>>(), state.settings.dj_role)).await;
+        permissions.retain(|(_, level)| *level != PermissionLevel::Everyone);
+        permissions.sort();
+
+        let mut description = match dj_role {
+            Some(dj_role) => format!("DJ role: <@&{}>\n", dj_role),
+            None => "DJ role: *none*, DJ commands are open to everyone\n".to_owned()
+        };
+        for (command, level) in permissions {
+            description.push_str(&format!("\n`{}` - {}", command, level.name()));
+        }
+        let _ = send_timed_reply(&ctx, description, Some(std::time::Duration::from_secs(30))).await;
+        return Ok(());
+    };
+    if !ctx.framework().options().commands.iter().any(|registered| registered.name == command) { return Err(CommandError::UnknownCommand); }
+
+    let level = match level {
+        Some(level) => {
+            guild_states.update_settings(guild_id, |settings| settings.permissions.insert(command.clone(), level)).await;
+            level
+        },
+        None => guild_states.with(guild_id, |state| state.settings.permissions.get(&command).copied()).await.unwrap_or(PermissionLevel::Everyone)
+    };
+    let _ = send_timed_reply(&ctx, format!("`{}` requires: {}", command, level.name()), None).await;
+    Ok(())
+}
+
+// sets the dj role, without arguments removes it
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn dj(ctx: Context<'_>, role: Option<Role>) -> Result<(), CommandError> {
+    let guild_id = ctx.guild_id().unwrap().get();
+    let dj_role = role.map(|role| role.id.get());
+    ctx.data().guild_states.update_settings(guild_id, |settings| settings.dj_role = dj_role).await;
+
+    let message = match dj_role {
+        Some(dj_role) => format!("DJ role set to <@&{}>", dj_role),
+        None => "DJ role removed".to_owned()
+    };
+    let _ = send_timed_reply(&ctx, message, None).await;
+    Ok(())
+}
@@ -22,12 +22,44 @@ pub enum CommandError {
     Track(#[from] songbird::tracks::ControlError),
     #[error("{0}")]
     Conversion(#[from] crate::convert_query::ConversionError),
-    #[error("")]
+    #[error("There's nothing in the playlist which can be played")]
     EmptyPlaylist,
     #[error("{0}")]
     Metadata(#[from] crate::metadata::MetadataError),
     #[error("Invalid query")]
     InvalidQuery,
+    #[error("Nothing was found")]
+    NoResults,
+    #[error("Nothing has been played yet")]
+    EmptyHistory,
+    #[error("Invalid track index")]
+    InvalidIndex,
+    #[error("Volume has to be between 0 and 200")]
+    InvalidVolume,
+    #[error("Percentage has to be between 0 and 100")]
+    InvalidPercentage,
+    #[error("You don't have permission to use this command")]
+    MissingPermissions,
+    #[error("Unknown command")]
+    UnknownCommand,
+    #[error("Prefix has to be up to 5 characters long without spaces")]
+    InvalidPrefix,
+    #[error("The queue is empty")]
+    EmptyQueue,
+    #[error("Playlist not found")]
+    PlaylistNotFound,
+    #[error("Playlist names have to be up to 32 characters long")]
+    InvalidPlaylistName,
+    #[error("{0}")]
+    Library(#[from] crate::library::LibraryError),
+    #[error("{0}")]
+    Import(#[from] crate::queue_export::ImportError),
+    #[error("")]
+    Storage(#[from] crate::storage::StorageError),
+    #[error("Invalid timestamp")]
+    InvalidTimestamp,
+    #[error("This track can't be seeked")]
+    Unseekable,
 }
 
 impl From<songbird::error::JoinError> for CommandError {
@@ -0,0 +1,23 @@
+use crate::{data::Context, playlists::entries_from_queue, queue_export::{export_queue, ExportFormat}};
+use poise::CreateReply;
+use serenity::builder::CreateAttachment;
+
+use crate::commands::error::{VoiceError, CommandError};
+
+// sends the queue as a file, attaching it to /play adds the tracks back
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn export(ctx: Context<'_>, format: Option<ExportFormat>) -> Result<(), CommandError> {
+    let format = format.unwrap_or_default();
+    let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
+    let handler = manager.get(ctx.guild_id().unwrap()).ok_or(CommandError::EmptyQueue)?;
+    let track_handles = handler.lock().await.queue().current_queue();
+
+    let entries = entries_from_queue(track_handles).await;
+    if entries.is_empty() { return Err(CommandError::EmptyQueue); }
+
+    ctx.send(
+        CreateReply::default()
+        .attachment(CreateAttachment::bytes(export_queue(entries, format), format.filename()))
+    ).await?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use std::sync::PoisonError;
+
+use crate::{data::Context, audio_processing::FilterPreset};
+use poise::ChoiceParameter;
+
+use crate::commands::{ error::{VoiceError, CommandError}, utils::{same_voice_channel, send_timed_reply} };
+
+// applies an audio filter preset, "clear" removes it
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn filter(ctx: Context<'_>, preset: Option<FilterPreset>) -> Result<(), CommandError> {
+    let guild = ctx.guild().unwrap().clone();
+    let audio_settings = ctx.data().guild_states.with(guild.id.get(), |state| state.settings.audio_settings.clone()).await;
+
+    let Some(preset) = preset else {
+        let preset = audio_settings.read().unwrap_or_else(PoisonError::into_inner).filter;
+        let _ = send_timed_reply(&ctx, format!("Filter: {}", preset.name()), None).await;
+        return Ok(());
+    };
+
+    let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
+    if let Some(handler) = manager.get(guild.id) {
+        if !same_voice_channel(&guild, &ctx.author().id, handler.clone()).await { return Ok(()); }
+    }
+
+    ctx.data().guild_states.update_settings(guild.id.get(), |settings| settings.audio_settings.write().unwrap_or_else(PoisonError::into_inner).filter = preset).await;
+    let message = match preset {
+        FilterPreset::Off => "Filter cleared".to_owned(),
+        preset => format!("Filter set to {}", preset.name())
+    };
+    let _ = send_timed_reply(&ctx, message, None).await;
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use crate::{data::Context, metadata::TrackMetadata};
+use poise::CreateReply;
+use serenity::{ builder::{ CreateEmbed, CreateEmbedFooter, CreateAllowedMentions }, model::Color };
+use std::time::Duration;
+use futures::stream::*;
+use crate::commands::{ error::CommandError, queue::{create_buttons, TRACKS_PER_PAGE, CHARACTERS_PER_FIELD_LINE} };
+
+// shows recently played tracks, newest first
+#[poise::command(slash_command, prefix_command, guild_only, ephemeral)]
+pub async fn history(ctx: Context<'_>, page: Option<usize>) -> Result<(), CommandError> {
+    let mut page = page.unwrap_or(1).max(1);
+    page -= 1; // represent the page as an index
+
+    let guild_id = ctx.guild_id().unwrap().get();
+    let mut history = read_history(&ctx, guild_id).await;
+    let mut last_page = (history.len() as f32 / TRACKS_PER_PAGE as f32).ceil() as usize;
+    page = page.min(last_page.max(1) - 1);
+
+    let reply_handle = ctx.send(CreateReply::default()
+        .reply(true)
+        .allowed_mentions(CreateAllowedMentions::new()
+            .replied_user(true))
+        .embed(create_history_embed(&history, page, last_page))
+        .components(vec![create_buttons(page, last_page)])
+    ).await?;
+
+    let mut collector = reply_handle.message().await?.await_component_interactions(ctx)
+        .timeout(Duration::from_secs(30))
+        .author_id(ctx.author().id)
+        .stream();
+
+    while let Some(message_collector) = collector.next().await {
+        match message_collector.data.custom_id.as_str() {
+            "prev" => page = page.saturating_sub(1),
+            "next" => page += 1,
+            "reload" => {
+                history = read_history(&ctx, guild_id).await;
+                last_page = (history.len() as f32 / TRACKS_PER_PAGE as f32).ceil() as usize;
+                page = page.min(last_page.max(1) - 1);
+            },
+            _ => continue
+        }
+        reply_handle.edit(ctx, CreateReply::default().embed(create_history_embed(&history, page, last_page)).components(vec![create_buttons(page, last_page)])).await?;
+        let _ = message_collector.defer(ctx).await;
+    }
+    ctx.data().add_to_cleanup(reply_handle, Duration::ZERO).await;
+    Ok(())
+}
+
+async fn read_history(ctx: &Context<'_>, guild_id: u64) -> Vec<TrackMetadata> {
+    ctx.data().guild_states.with(guild_id, |state| state.history.iter().rev().cloned().collect()).await
+}
+
+fn create_history_embed(history: &[TrackMetadata], page: usize, last_page: usize) -> CreateEmbed {
+    let mut played = String::new();
+    for (i, track_metadata) in history.iter().enumerate().skip(page * TRACKS_PER_PAGE).take(TRACKS_PER_PAGE) {
+        let max_num_on_page_length = (i + 1) / 100 + 1;
+        let stringified_metadata = track_metadata.video_metadata.to_queue_string(None, Some(CHARACTERS_PER_FIELD_LINE - max_num_on_page_length));
+        played.push_str(&format!("{}. {}\n", i + 1, stringified_metadata));
+    }
+    if played.is_empty() { played = "*Nothing*".to_owned(); }
+
+    CreateEmbed::default()
+        .color(Color::PURPLE)
+        .title("History")
+        .field("Recently Played:", played, false)
+        .footer(CreateEmbedFooter::new(format!("Page: {}/{}  Tracks: {}", page + 1, last_page.max(1), history.len())))
+}
@@ -1,9 +1,12 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
-use crate::{data::Context, metadata::{LazyMetadataEventHandler, LazyMetadata, TrackMetadata, VideoMetadata, UserMetadata}, commands::error::CommandError};
+use crate::{data::Context, audio_processing::process_track, metadata::{LazyMetadata, TrackMetadata, VideoMetadata, UserMetadata}, commands::error::CommandError};
 use poise::CreateReply;
 use serenity::{builder::{CreateAllowedMentions, CreateEmbed}, model::Color};
-use crate::commands::{error::VoiceError, utils::should_move_channels};
+use songbird::tracks::Track;
+use tokio::sync::RwLock;
+use typemap::ShareMap;
+use crate::commands::{error::VoiceError, utils::{should_move_channels, add_global_events, announcement_cleanup_delay}};
 
 // tells a joke from jeja.pl
 #[poise::command(slash_command, prefix_command, guild_only)]
@@ -25,7 +28,7 @@ pub async fn jeja(ctx: Context<'_>) -> Result<(), CommandError> {
 
         // add event handler upon joining a channel
         if connection.is_none() { 
-            handler_guard.add_global_event(songbird::Event::Track(songbird::TrackEvent::Play), LazyMetadataEventHandler { handler: handler.clone(), channel_id: ctx.channel_id(), http: ctx.serenity_context().http.clone() });
+            add_global_events(&ctx, &mut handler_guard, handler.clone()).await;
         }
 
         let _ = handler_guard.deafen(true).await; 
@@ -47,7 +50,9 @@ pub async fn jeja(ctx: Context<'_>) -> Result<(), CommandError> {
     };
 
     let input = crate::convert_query::YouTubeComposer::Metadata { metadata: track_metadata.video_metadata.clone(), client: ctx.data().reqwest_client.clone() }.into();
-    let mut track_handle = handler.lock().await.enqueue_input(input).await;
+    let audio_settings = ctx.data().guild_states.with(guild.id.get(), |state| state.settings.audio_settings.clone()).await;
+    let track = process_track(Track::new_with_data(input, Arc::new(RwLock::new(ShareMap::custom()))), &audio_settings);
+    let mut track_handle = handler.lock().await.enqueue(track).await;
     track_handle.write_lazy_metadata(track_metadata.clone()).await;
 
     if !was_empty {
@@ -61,7 +66,7 @@ pub async fn jeja(ctx: Context<'_>) -> Result<(), CommandError> {
                     .description(track_metadata.video_metadata.title)
                     .color(Color::PURPLE))
             ).await?;
-            ctx.data().add_to_cleanup(reply_handle, std::time::Duration::from_secs(10)).await;
+            ctx.data().add_to_cleanup(reply_handle, announcement_cleanup_delay(&ctx).await).await;
     }
     Ok(())
 }
\ No newline at end of file
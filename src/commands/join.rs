@@ -1,6 +1,6 @@
-use crate::{data::Context, metadata::LazyMetadataEventHandler, commands::error::CommandError};
+use crate::{data::Context, commands::error::CommandError};
 
-use crate::commands::{error::VoiceError, utils::should_move_channels};
+use crate::commands::{error::VoiceError, utils::{should_move_channels, add_global_events}};
 
 // joins the voice channel
 #[poise::command(slash_command, prefix_command, guild_only)]
@@ -21,7 +21,7 @@ pub async fn join(ctx: Context<'_>) -> Result<(), CommandError> {
 
     // add event handler upon joining a channel
     if connection.is_none() { 
-        handler_guard.add_global_event(songbird::Event::Track(songbird::TrackEvent::Play), LazyMetadataEventHandler { handler: handler.clone(), channel_id: ctx.channel_id(), http: ctx.serenity_context().http.clone() });
+        add_global_events(&ctx, &mut handler_guard, handler.clone()).await;
     }
 
     let _ = handler_guard.deafen(true).await; 
@@ -0,0 +1,28 @@
+use crate::data::Context;
+
+use crate::commands::{ error::{VoiceError, CommandError}, utils::same_voice_channel };
+
+// skips to a track in the queue, dropping every track before it
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn jump(ctx: Context<'_>, index: usize) -> Result<(), CommandError> {
+    let guild = ctx.guild().unwrap().clone();
+    let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
+
+    if index == 0 { return Err(CommandError::InvalidIndex); }
+
+    if let Some(handler) = manager.get(guild.id) {
+        if !same_voice_channel(&guild, &ctx.author().id, handler.clone()).await { return Ok(()); }
+
+        let handler_guard = handler.lock().await;
+        let skipped = handler_guard.queue().modify_queue(|queue| {
+            if index >= queue.len() { return None; }
+            Some(queue.drain(1..index).collect::<Vec<_>>())
+        }).ok_or(CommandError::InvalidIndex)?;
+
+        for track in skipped.iter() {
+            let _ = track.stop();
+        }
+        handler_guard.queue().skip()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use crate::{data::Context, library::{LibraryGroup, LOCAL_PREFIX}, playlists::PlaylistEntry, utils::format_duration};
+
+use crate::commands::{ error::CommandError, playlist::enqueue_entries, utils::{join_author_channel, send_timed_reply} };
+
+const RESULTS_SHOWN: usize = 10;
+
+// the shared local music library
+#[poise::command(slash_command, prefix_command, guild_only, subcommands("search", "play", "rescan"), subcommand_required)]
+pub async fn library(_ctx: Context<'_>) -> Result<(), CommandError> {
+    Ok(())
+}
+
+// lists library tracks matching the query
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn search(ctx: Context<'_>, query: Vec<String>) -> Result<(), CommandError> {
+    let tracks = ctx.data().library.search(&query.join(" ")).await?;
+
+    let mut lines = tracks.iter().take(RESULTS_SHOWN).enumerate().map(|(index, track)| {
+        let video_metadata = track.video_metadata();
+        match &track.album {
+            Some(album) => format!("{}. {} | {} | *{}*", index + 1, video_metadata.title, format_duration(video_metadata.duration, None), album),
+            None => format!("{}. {} | {}", index + 1, video_metadata.title, format_duration(video_metadata.duration, None))
+        }
+    }).collect::<Vec<String>>();
+    if tracks.len() > RESULTS_SHOWN {
+        lines.push(format!("*...and {} more*", tracks.len() - RESULTS_SHOWN));
+    }
+    lines.push(format!("Use `/play {}<query>` to play the first match", LOCAL_PREFIX));
+
+    let _ = send_timed_reply(&ctx, lines.join("\n"), Some(std::time::Duration::from_secs(30))).await;
+    Ok(())
+}
+
+// adds every library track of an album or an artist to the queue
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn play(ctx: Context<'_>, group: LibraryGroup, name: Vec<String>) -> Result<(), CommandError> {
+    let guild = ctx.guild().unwrap().clone();
+    let tracks = ctx.data().library.group(group, &name.join(" ")).await?;
+
+    let handler = join_author_channel(&ctx, &guild).await?;
+    let entries = tracks.iter().map(|track| PlaylistEntry::Track(track.video_metadata())).collect();
+    let (added, _) = enqueue_entries(&ctx, handler, entries).await;
+
+    let _ = send_timed_reply(&ctx, format!("Added {} tracks from the library", added), None).await;
+    Ok(())
+}
+
+// indexes the library directory again, only the bot owners can do this since the library is shared by every server
+#[poise::command(slash_command, prefix_command, guild_only, owners_only)]
+pub async fn rescan(ctx: Context<'_>) -> Result<(), CommandError> {
+    ctx.defer_ephemeral().await?; // scanning a large library can take a while
+    let tracks_len = ctx.data().library.scan().await?;
+    let _ = send_timed_reply(&ctx, format!("Indexed {} tracks", tracks_len), None).await;
+    Ok(())
+}
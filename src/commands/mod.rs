@@ -11,5 +11,24 @@ pub mod stop;
 pub mod register;
 pub mod help;
 pub mod join;
+pub mod seek;
+pub mod shuffle;
+pub mod remove;
+pub mod _move;
+pub mod jump;
+pub mod clear;
+pub mod previous;
+pub mod replay;
+pub mod history;
+pub mod volume;
+pub mod normalize;
+pub mod filter;
+pub mod voteskip;
+pub mod config;
+pub mod playlist;
+pub mod export;
+pub mod library;
+pub mod podcast;
+pub mod search;
 pub mod error;
 pub mod utils;
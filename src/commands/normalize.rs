@@ -0,0 +1,25 @@
+use std::sync::PoisonError;
+
+use crate::data::Context;
+
+use crate::commands::{ error::{VoiceError, CommandError}, utils::{same_voice_channel, send_timed_reply} };
+
+// evens out loudness differences between tracks, without arguments toggles it
+#[poise::command(slash_command, prefix_command, guild_only, aliases("norm"))]
+pub async fn normalize(ctx: Context<'_>, enabled: Option<bool>) -> Result<(), CommandError> {
+    let guild = ctx.guild().unwrap().clone();
+    let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
+    if let Some(handler) = manager.get(guild.id) {
+        if !same_voice_channel(&guild, &ctx.author().id, handler.clone()).await { return Ok(()); }
+    }
+
+    let enabled = ctx.data().guild_states.update_settings(guild.id.get(), |settings| {
+        let mut audio_settings = settings.audio_settings.write().unwrap_or_else(PoisonError::into_inner);
+        audio_settings.normalization = enabled.unwrap_or(!audio_settings.normalization);
+        audio_settings.normalization
+    }).await;
+
+    let message = if enabled { "Normalization enabled" } else { "Normalization disabled" };
+    let _ = send_timed_reply(&ctx, message, None).await;
+    Ok(())
+}
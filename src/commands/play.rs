@@ -1,19 +1,86 @@
-use std::sync::Arc;
+use std::{collections::{HashSet, VecDeque}, sync::Arc};
 
-use crate::{data::Context, convert_query::{ConvertedQuery, MetaInput, PendingMetaInput}, metadata::{LazyMetadata, LazyMetadataEventHandler}, utils::format_duration};
+use crate::{data::{Cleanup, Context}, queue_persistence::persist_queue, audio_probe::is_audio_url, audio_processing::{process_track, SharedAudioSettings}, convert_query::{extract_media_type, ConversionError, ConvertedQuery, MediaType, MetaInput, PendingMetaInput, PendingPages}, metadata::LazyMetadata, queue_export::{import_queue, ImportError, MAX_IMPORT_SIZE}, utils::format_duration};
 use poise::CreateReply;
-use serenity::{model::Color, builder::{CreateEmbed, CreateAllowedMentions}};
+use serenity::{all::{Attachment, ButtonStyle}, model::Color, builder::{CreateActionRow, CreateButton, CreateEmbed, CreateAllowedMentions, CreateMessage}};
 use songbird::{Call, tracks::TrackHandle, tracks::Track};
 use tokio::sync::{Mutex, RwLock};
 use crate::commands::{
-    utils::should_move_channels,
-    error::{VoiceError, CommandError}
+    utils::{should_move_channels, add_global_events, announcement_cleanup_delay, join_author_channel, send_timed_reply},
+    error::{VoiceError, CommandError},
+    playlist::enqueue_entries
 };
 use typemap::ShareMap;
+use url::Url;
 
-// plays audio from an url or a search query
+const PLAYLIST_PROMPT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+// where newly added tracks end up in the queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Insertion {
+    Back,
+    Next,
+    Now
+}
+
+// plays audio from an url, a search query or an attached audio file, or imports a queue file exported with /export
 #[poise::command(slash_command, prefix_command, guild_only, aliases("p"))]
-pub async fn play(ctx: Context<'_>, query: Vec<String>) -> Result<(), CommandError> {
+pub async fn play(ctx: Context<'_>, query: Vec<String>, attachment: Option<Attachment>) -> Result<(), CommandError> {
+    match attachment {
+        Some(attachment) if query.is_empty() => {
+            // attachment urls end with the file name, so they get handled like any other direct link
+            match Url::parse(&attachment.url).is_ok_and(|url| is_audio_url(&url)) {
+                true => play_with_insertion(ctx, vec![attachment.url], Insertion::Back).await,
+                false => import_attachment(ctx, attachment).await
+            }
+        },
+        _ => play_with_insertion(ctx, query, Insertion::Back).await
+    }
+}
+
+// plays audio right after the current track
+#[poise::command(slash_command, prefix_command, guild_only, aliases("pn"))]
+pub async fn playnext(ctx: Context<'_>, query: Vec<String>) -> Result<(), CommandError> {
+    play_with_insertion(ctx, query, Insertion::Next).await
+}
+
+// plays audio immediately, skipping the current track
+#[poise::command(slash_command, prefix_command, guild_only, aliases("pnow"))]
+pub async fn playnow(ctx: Context<'_>, query: Vec<String>) -> Result<(), CommandError> {
+    play_with_insertion(ctx, query, Insertion::Now).await
+}
+
+// a link to a video inside of a playlist could mean either, so the author gets asked which one
+async fn choose_video_or_playlist(ctx: &Context<'_>, media_type: MediaType) -> Result<MediaType, CommandError> {
+    let MediaType::YouTubeVideoInPlaylist { video_id, playlist_id, index, start } = media_type else { return Ok(media_type); };
+
+    let reply_handle = ctx.send(CreateReply::default()
+        .reply(true)
+        .embed(CreateEmbed::new().description("This video is a part of a playlist, what should be queued?").color(Color::PURPLE))
+        .components(vec![CreateActionRow::Buttons(vec![
+            CreateButton::new("video").label("Only this video").style(ButtonStyle::Primary),
+            CreateButton::new("playlist").label("Whole playlist").style(ButtonStyle::Secondary)
+        ])])
+    ).await?;
+    let message = reply_handle.message().await?;
+
+    // the video is queued if nobody answers
+    let interaction = message.await_component_interaction(ctx)
+        .author_id(ctx.author().id)
+        .timeout(PLAYLIST_PROMPT_TIMEOUT)
+        .await;
+    if let Some(interaction) = &interaction {
+        let _ = interaction.defer(ctx).await;
+    }
+    let _ = reply_handle.delete(*ctx).await;
+
+    Ok(match interaction {
+        Some(interaction) if interaction.data.custom_id == "playlist" => MediaType::YouTubePlaylist { playlist_id, index },
+        _ => MediaType::YouTubeVideo { video_id, start }
+    })
+}
+
+pub async fn play_with_insertion(ctx: Context<'_>, query: Vec<String>, insertion: Insertion) -> Result<(), CommandError> {
     if query.len() == 0 { return Err(CommandError::InvalidQuery) }
     let query = query.join(" "); // represent query as a string vector so spaces are allowed
     let guild = ctx.guild().unwrap().clone();
@@ -23,6 +90,8 @@ pub async fn play(ctx: Context<'_>, query: Vec<String>) -> Result<(), CommandErr
     let handler = manager.get_or_insert(guild.id);
 
     if !should_move_channels(&ctx, &guild, user_voice).await { return Err(VoiceError::DifferentVoiceChannel.into()) }
+
+    let media_type = choose_video_or_playlist(&ctx, extract_media_type(&query).map_err(ConversionError::from)?).await?;
    
     let connection = handler.lock().await.current_connection().and_then(|conn| conn.channel_id);
 
@@ -33,7 +102,7 @@ pub async fn play(ctx: Context<'_>, query: Vec<String>) -> Result<(), CommandErr
 
         // add event handler upon joining a channel
         if connection.is_none() { 
-            handler_guard.add_global_event(songbird::Event::Track(songbird::TrackEvent::Play), LazyMetadataEventHandler { handler: handler.clone(), channel_id: ctx.channel_id(), http: ctx.serenity_context().http.clone() });
+            add_global_events(&ctx, &mut handler_guard, handler.clone()).await;
         }
 
         let _ = handler_guard.deafen(true).await; 
@@ -41,17 +110,27 @@ pub async fn play(ctx: Context<'_>, query: Vec<String>) -> Result<(), CommandErr
         handler_guard.queue().is_empty()
     };
     
-    let converted_query = ctx.data().convert_query(&query, ctx.author().into()).await?;
+    let converted_query = ctx.data().convert_media_type(media_type, ctx.author().into()).await?;
+    add_converted_query(ctx, handler, converted_query, was_empty, insertion).await
+}
+
+// enqueues a converted query and replies with what was added
+pub async fn add_converted_query(ctx: Context<'_>, handler: Arc<Mutex<Call>>, converted_query: ConvertedQuery, was_empty: bool, insertion: Insertion) -> Result<(), CommandError> {
+    let guild_id = ctx.guild_id().unwrap();
+    let audio_settings = ctx.data().guild_states.with(guild_id.get(), |state| state.settings.audio_settings.clone()).await;
 
     match converted_query {
         ConvertedQuery::LiveVideo(metainput) => {
             let track_metadata = metainput.track_metadata.clone();
 
-            add_live_video(handler.clone(), metainput).await;
+            add_live_video(handler.clone(), metainput, &audio_settings).await;
+            if !was_empty { insert(handler.clone(), 1, insertion).await?; }
 
             let video_metadata = &track_metadata.video_metadata;
             let description = match &video_metadata.audio_source {
                 crate::metadata::AudioSource::YouTube { video_id } => format!("[{}](https://youtu.be/{}) | {}", video_metadata.title, video_id, format_duration(video_metadata.duration, None)),
+                crate::metadata::AudioSource::Radio { url, .. } => format!("[{}]({}) | {}", video_metadata.display_title(), url, video_metadata.formatted_duration()),
+                crate::metadata::AudioSource::Url { url } | crate::metadata::AudioSource::SoundCloud { url, .. } | crate::metadata::AudioSource::Bandcamp { url } => format!("[{}]({}) | {}", video_metadata.title, url, format_duration(video_metadata.duration, None)),
                 crate::metadata::AudioSource::File { .. } => format!("{} | {}", video_metadata.title, format_duration(video_metadata.duration, None)),
                 crate::metadata::AudioSource::Jeja { .. } => video_metadata.title.clone()
             };
@@ -67,10 +146,11 @@ pub async fn play(ctx: Context<'_>, query: Vec<String>) -> Result<(), CommandErr
                         .description(description)
                         .color(Color::PURPLE))
                 ).await?;
-                ctx.data().add_to_cleanup(reply_handle, std::time::Duration::from_secs(10)).await;
+                ctx.data().add_to_cleanup(reply_handle, announcement_cleanup_delay(&ctx).await).await;
             }
         },
-        ConvertedQuery::LivePlaylist(metainputs) => {
+        ConvertedQuery::LivePlaylist(metainputs, skipped) => {
+            if metainputs.is_empty() { return Err(CommandError::EmptyPlaylist); }
             let metainputs_len = metainputs.len();
 
             match was_empty {
@@ -78,12 +158,13 @@ pub async fn play(ctx: Context<'_>, query: Vec<String>) -> Result<(), CommandErr
                     let mut metainputs_iter = metainputs.into_iter();
                     let metainput = metainputs_iter.next().ok_or(CommandError::EmptyPlaylist)?;
 
-                    add_live_video(handler.clone(), metainput).await;
+                    add_live_video(handler.clone(), metainput, &audio_settings).await;
 
-                    add_live_videos(handler, metainputs_iter).await;
+                    add_live_videos(handler, metainputs_iter, &audio_settings).await;
                 },
                 false => { // else we push everything to a buffer
-                    add_live_videos(handler, metainputs.into_iter()).await;
+                    add_live_videos(handler.clone(), metainputs.into_iter(), &audio_settings).await;
+                    insert(handler, metainputs_len, insertion).await?;
                 }
             };
 
@@ -93,29 +174,39 @@ pub async fn play(ctx: Context<'_>, query: Vec<String>) -> Result<(), CommandErr
                 .reply(true)
                 .allowed_mentions(CreateAllowedMentions::new()
                     .replied_user(true))
-                .embed(CreateEmbed::new()
-                    .title(format!("Added {} Tracks:", metainputs_len))
-                    .color(Color::PURPLE))
+                .embed(added_tracks_embed(metainputs_len, skipped))
             ).await?;
 
-            ctx.data().add_to_cleanup(reply_handle, std::time::Duration::from_secs(10)).await;
+            ctx.data().add_to_cleanup(reply_handle, announcement_cleanup_delay(&ctx).await).await;
         },
-        ConvertedQuery::PendingPlaylist(pending_metainputs) => {
+        ConvertedQuery::PendingPlaylist(mut pending_metainputs, mut skipped, mut pending_pages) => {
+            // a first page without anything playable (e.g. only episodes) is replaced by the next one
+            while pending_metainputs.is_empty() {
+                let next_page = match &mut pending_pages {
+                    Some(pending_pages) => pending_pages.next().await,
+                    None => None
+                };
+                let Some((next_metainputs, next_skipped)) = next_page else { return Err(CommandError::EmptyPlaylist); };
+                pending_metainputs = next_metainputs;
+                skipped += next_skipped;
+            }
             let metainputs_len = pending_metainputs.len();
 
-            match was_empty {
+            let last_track_handle = match was_empty {
                 true => { // if the queue was empty we immediately generate metadata, enqueue the first track and push the rest to a buffer
                     let mut pending_metainputs_iter = pending_metainputs.into_iter();
                     
                     let pending_metainput = pending_metainputs_iter.next().ok_or(CommandError::EmptyPlaylist)?;
 
-                    let mut first_track_handle = add_pending_video(handler.clone(), pending_metainput).await;
+                    let mut first_track_handle = add_pending_video(handler.clone(), pending_metainput, &audio_settings).await;
                     first_track_handle.awake_lazy_metadata().await?;
                     
-                    add_pending_videos(handler, pending_metainputs_iter).await;
+                    add_pending_videos(handler.clone(), pending_metainputs_iter, &audio_settings).await.or(Some(first_track_handle))
                 },
                 false => { // else we push everything to a buffer
-                    add_pending_videos(handler, pending_metainputs.into_iter()).await;
+                    let last_track_handle = add_pending_videos(handler.clone(), pending_metainputs.into_iter(), &audio_settings).await;
+                    insert(handler.clone(), metainputs_len, insertion).await?;
+                    last_track_handle
                 }
             };
             
@@ -125,49 +216,187 @@ pub async fn play(ctx: Context<'_>, query: Vec<String>) -> Result<(), CommandErr
                 .reply(true)
                 .allowed_mentions(CreateAllowedMentions::new()
                     .replied_user(true))
-                .embed(CreateEmbed::new()
-                    .title(format!("Added {} Tracks:", metainputs_len))
-                    .color(Color::PURPLE))
+                .embed(added_tracks_embed(metainputs_len, skipped))
             ).await?;
-            ctx.data().add_to_cleanup(reply_handle, std::time::Duration::from_secs(10)).await;
+            ctx.data().add_to_cleanup(reply_handle, announcement_cleanup_delay(&ctx).await).await;
+
+            // the rest of the playlist is kept right after the first page unless it's added at the back anyway
+            let Some(pending_pages) = pending_pages else { return Ok(()); };
+            let after = last_track_handle.filter(|_| !was_empty && insertion != Insertion::Back);
+
+            // loaded in the background, the command is done (and the queue saved) once the first page is in
+            let data = ctx.data();
+            let (queue_store, guild_states, cleanups) = (data.queue_store.clone(), data.guild_states.clone(), data.cleanups.clone());
+            let (http, channel_id) = (ctx.serenity_context().http.clone(), ctx.channel_id());
+            let delay = announcement_cleanup_delay(&ctx).await;
+            tokio::spawn(async move {
+                let (added, skipped) = enqueue_remaining_pages(handler.clone(), pending_pages, after, &audio_settings).await;
+                persist_queue(&queue_store, Some(handler), &guild_states, guild_id.get()).await;
+                if added == 0 && skipped == 0 { return; }
+
+                let Ok(message) = channel_id.send_message(&http, CreateMessage::new().embed(added_tracks_embed(added, skipped))).await else { return; };
+                cleanups.lock().await.push(Cleanup { message: message.clone(), delay });
+                tokio::time::sleep(delay).await;
+                let _ = message.delete(&http).await;
+                cleanups.lock().await.retain(|pending| pending.message.id != message.id);
+            });
         }
     }
     
     Ok(())
 }
 
-async fn add_live_video(handler: Arc<Mutex<Call>>, metainput: MetaInput) -> TrackHandle {
-    let MetaInput { input, track_metadata } = metainput;
-    let track = Track::new_with_data(input, Arc::new(RwLock::new(ShareMap::custom())));
+// entries which couldn't be added (e.g. episodes in spotify playlists) are mentioned instead of failing the whole playlist
+fn added_tracks_embed(count: usize, skipped: usize) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .title(format!("Added {} Tracks:", count))
+        .color(Color::PURPLE);
+    if skipped > 0 {
+        embed = embed.description(format!("Skipped {} entries which can't be played or go past the playlist limit", skipped));
+    }
+    embed
+}
+
+// adds the tracks of a queue file at the back of the queue
+async fn import_attachment(ctx: Context<'_>, attachment: Attachment) -> Result<(), CommandError> {
+    if attachment.size > MAX_IMPORT_SIZE { return Err(ImportError::TooLarge.into()); }
+    let guild = ctx.guild().unwrap().clone();
+
+    ctx.defer_ephemeral().await?; // converting queries can take a while
+    let content = attachment.download().await?;
+    let entries = import_queue(&attachment.filename, &content)?;
+
+    let handler = join_author_channel(&ctx, &guild).await?;
+    let (added, skipped) = enqueue_entries(&ctx, handler, entries).await;
+
+    let mut message = format!("Imported {} tracks from `{}`", added, attachment.filename);
+    if skipped > 0 {
+        message.push_str(&format!(", {} entries couldn't be loaded", skipped));
+    }
+    let _ = send_timed_reply(&ctx, message, None).await;
+    Ok(())
+}
+
+// enqueues everything a query was converted to at the back of the queue, returns how many tracks were added
+pub async fn enqueue_converted_query(handler: Arc<Mutex<Call>>, converted_query: ConvertedQuery, audio_settings: &SharedAudioSettings) -> usize {
+    match converted_query {
+        ConvertedQuery::LiveVideo(metainput) => {
+            add_live_video(handler, metainput, audio_settings).await;
+            1
+        },
+        ConvertedQuery::LivePlaylist(metainputs, _) => {
+            let metainputs_len = metainputs.len();
+            add_live_videos(handler, metainputs.into_iter(), audio_settings).await;
+            metainputs_len
+        },
+        ConvertedQuery::PendingPlaylist(pending_metainputs, _, pending_pages) => {
+            let metainputs_len = pending_metainputs.len();
+            add_pending_videos(handler.clone(), pending_metainputs.into_iter(), audio_settings).await;
+            match pending_pages {
+                Some(pending_pages) => metainputs_len + enqueue_remaining_pages(handler, pending_pages, None, audio_settings).await.0,
+                None => metainputs_len
+            }
+        }
+    }
+}
+
+// moves the last `count` tracks right after the current one and skips to them if requested
+pub async fn insert(handler: Arc<Mutex<Call>>, count: usize, insertion: Insertion) -> Result<(), CommandError> {
+    if insertion == Insertion::Back { return Ok(()); }
+
+    let handler_guard = handler.lock().await;
+    let moved = handler_guard.queue().modify_queue(|queue| {
+        if count == 0 || queue.len() <= count { return false; } // nothing was added or the tracks are already at the front
+        let mut inserted = queue.split_off(queue.len() - count);
+        let mut rest = queue.split_off(1);
+        queue.append(&mut inserted);
+        queue.append(&mut rest);
+        true
+    });
+
+    if moved && insertion == Insertion::Now {
+        handler_guard.queue().skip()?;
+    }
+    Ok(())
+}
+
+async fn add_live_video(handler: Arc<Mutex<Call>>, metainput: MetaInput, audio_settings: &SharedAudioSettings) -> TrackHandle {
+    let MetaInput { input, track_metadata, start } = metainput;
+    let track = process_track(Track::new_with_data(input, Arc::new(RwLock::new(ShareMap::custom()))), audio_settings);
     let mut track_handle = handler.lock().await.enqueue(track).await;
     track_handle.write_lazy_metadata(track_metadata).await;
+    if let Some(start) = start {
+        let _ = track_handle.seek(start); // the seek is applied once the track gets created
+    }
     track_handle
 }
 
-async fn add_pending_video(handler: Arc<Mutex<Call>>, pending_metainput: PendingMetaInput) -> TrackHandle {
-    let PendingMetaInput { input, query, added_by } = pending_metainput;
-    let track = Track::new_with_data(input, Arc::new(RwLock::new(ShareMap::custom())));
+async fn add_pending_video(handler: Arc<Mutex<Call>>, pending_metainput: PendingMetaInput, audio_settings: &SharedAudioSettings) -> TrackHandle {
+    let PendingMetaInput { input, query, added_by, spotify_track } = pending_metainput;
+    let track = process_track(Track::new_with_data(input, Arc::new(RwLock::new(ShareMap::custom()))), audio_settings);
     let mut track_handle = handler.lock().await.enqueue(track).await;
     track_handle.write_added_by(added_by).await;
     track_handle.write_query(query).await;
+    if let Some(track_data) = spotify_track {
+        track_handle.write_spotify_track(track_data).await;
+    }
     track_handle
 }
 
-async fn add_live_videos(handler: Arc<Mutex<Call>>, metainputs: std::vec::IntoIter<MetaInput>) {
+async fn add_live_videos(handler: Arc<Mutex<Call>>, metainputs: std::vec::IntoIter<MetaInput>, audio_settings: &SharedAudioSettings) {
     let mut handler_guard = handler.lock().await;
     for metainput in metainputs {
-        let track = Track::new_with_data(metainput.input, Arc::new(RwLock::new(ShareMap::custom())));
+        let track = process_track(Track::new_with_data(metainput.input, Arc::new(RwLock::new(ShareMap::custom()))), audio_settings);
         let mut track_handle = handler_guard.enqueue(track).await;
         track_handle.write_lazy_metadata(metainput.track_metadata).await;
     }
 }
 
-async fn add_pending_videos(handler: Arc<Mutex<Call>>, metainputs: std::vec::IntoIter<PendingMetaInput>) {
+// returns the handle of the last enqueued track
+async fn add_pending_videos(handler: Arc<Mutex<Call>>, metainputs: std::vec::IntoIter<PendingMetaInput>, audio_settings: &SharedAudioSettings) -> Option<TrackHandle> {
     let mut handler_guard = handler.lock().await;
+    enqueue_pending_videos(&mut handler_guard, metainputs, audio_settings).await.pop()
+}
+
+// returns the handles of the enqueued tracks in order
+async fn enqueue_pending_videos(handler_guard: &mut Call, metainputs: std::vec::IntoIter<PendingMetaInput>, audio_settings: &SharedAudioSettings) -> Vec<TrackHandle> {
+    let mut track_handles = vec![];
     for metainput in metainputs {
-        let track = Track::new_with_data(metainput.input, Arc::new(RwLock::new(ShareMap::custom())));
+        let track = process_track(Track::new_with_data(metainput.input, Arc::new(RwLock::new(ShareMap::custom()))), audio_settings);
         let mut track_handle = handler_guard.enqueue(track).await;
         track_handle.write_added_by(metainput.added_by).await;
         track_handle.write_query(metainput.query).await;
+        if let Some(track_data) = metainput.spotify_track {
+            track_handle.write_spotify_track(track_data).await;
+        }
+        track_handles.push(track_handle);
+    }
+    track_handles
+}
+
+// enqueues pages of a playlist as they finish loading, every page is moved right after `after` if it's given
+// returns how many tracks were added and how many entries were left out
+async fn enqueue_remaining_pages(handler: Arc<Mutex<Call>>, mut pending_pages: PendingPages, mut after: Option<TrackHandle>, audio_settings: &SharedAudioSettings) -> (usize, usize) {
+    let (mut added, mut skipped) = (0, 0);
+    while let Some((pending_metainputs, page_skipped)) = pending_pages.next().await {
+        let count = pending_metainputs.len();
+        // enqueued and moved under one lock, so other commands can't change the queue in between
+        let mut handler_guard = handler.lock().await;
+        let track_handles = enqueue_pending_videos(&mut handler_guard, pending_metainputs.into_iter(), audio_settings).await;
+        if let Some(previous_track_handle) = after.take() {
+            let previous_uuid = previous_track_handle.uuid();
+            let uuids = track_handles.iter().map(TrackHandle::uuid).collect::<HashSet<_>>();
+            handler_guard.queue().modify_queue(|queue| {
+                // the previous track could've been removed in the meantime, then the page stays at the back
+                let Some(index) = queue.iter().position(|queued| queued.uuid() == previous_uuid) else { return; };
+                let (mut inserted, mut rest): (VecDeque<_>, VecDeque<_>) = queue.drain(index + 1..).partition(|queued| uuids.contains(&queued.uuid()));
+                queue.append(&mut inserted);
+                queue.append(&mut rest);
+            });
+            after = track_handles.last().cloned().or(Some(previous_track_handle));
+        }
+        added += count;
+        skipped += page_skipped;
     }
+    (added, skipped)
 }
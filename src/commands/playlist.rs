@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use crate::{data::Context, audio_processing::process_track, metadata::{track_from_metadata, TrackMetadata}, playlists::{entries_from_queue, PlaylistEntry, PlaylistScope}, permissions::{has_permission, PermissionLevel}};
+use songbird::Call;
+use tokio::sync::Mutex;
+
+use crate::commands::{ error::{VoiceError, CommandError}, play::enqueue_converted_query, utils::{join_author_channel, send_timed_reply} };
+
+const MAX_NAME_LENGTH: usize = 32;
+const ENTRIES_SHOWN: usize = 20;
+
+// saved playlists, personal ones belong to a user and server ones are shared by everyone in the server
+#[poise::command(slash_command, prefix_command, guild_only, subcommands("save", "load", "list", "delete", "add"), subcommand_required)]
+pub async fn playlist(_ctx: Context<'_>) -> Result<(), CommandError> {
+    Ok(())
+}
+
+// saves the current queue as a playlist, replacing a playlist with the same name
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn save(ctx: Context<'_>, name: String, scope: Option<PlaylistScope>) -> Result<(), CommandError> {
+    let name = parse_name(&name)?;
+    let scope = scope.unwrap_or_default();
+    let owner_id = editable_owner_id(&ctx, scope).await?;
+
+    let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
+    let handler = manager.get(ctx.guild_id().unwrap()).ok_or(CommandError::EmptyQueue)?;
+    let track_handles = handler.lock().await.queue().current_queue();
+
+    let entries = entries_from_queue(track_handles).await;
+    if entries.is_empty() { return Err(CommandError::EmptyQueue); }
+
+    let entries_len = entries.len();
+    ctx.data().playlist_store.update(scope, owner_id, |playlists| playlists.insert(name.clone(), entries)).await?;
+    let _ = send_timed_reply(&ctx, format!("Saved {} tracks as `{}`", entries_len, name), None).await;
+    Ok(())
+}
+
+// adds a playlist to the queue
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn load(ctx: Context<'_>, name: String, scope: Option<PlaylistScope>) -> Result<(), CommandError> {
+    let name = parse_name(&name)?;
+    let scope = scope.unwrap_or_default();
+    let guild = ctx.guild().unwrap().clone();
+
+    let owner_id = owner_id(&ctx, scope);
+    let entries = ctx.data().playlist_store.read(scope, owner_id).await?.remove(&name).ok_or(CommandError::PlaylistNotFound)?;
+
+    ctx.defer_ephemeral().await?; // converting queries can take a while
+    let handler = join_author_channel(&ctx, &guild).await?;
+    let (added, skipped) = enqueue_entries(&ctx, handler, entries).await;
+
+    let mut message = format!("Added {} tracks from `{}`", added, name);
+    if skipped > 0 {
+        message.push_str(&format!(", {} entries couldn't be loaded", skipped));
+    }
+    let _ = send_timed_reply(&ctx, message, None).await;
+    Ok(())
+}
+
+// lists the playlists, or the tracks of a single one
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn list(ctx: Context<'_>, name: Option<String>, scope: Option<PlaylistScope>) -> Result<(), CommandError> {
+    let scope = scope.unwrap_or_default();
+    let playlists = ctx.data().playlist_store.read(scope, owner_id(&ctx, scope)).await?;
+
+    let description = match name {
+        Some(name) => {
+            let entries = playlists.get(&parse_name(&name)?).ok_or(CommandError::PlaylistNotFound)?;
+            let mut lines = entries.iter().take(ENTRIES_SHOWN).enumerate().map(|(index, entry)| match entry {
+                PlaylistEntry::Track(video_metadata) => format!("{}. {}", index + 1, video_metadata.title),
+                PlaylistEntry::Query(query) => format!("{}. `{}`", index + 1, query)
+            }).collect::<Vec<String>>();
+            if entries.len() > ENTRIES_SHOWN {
+                lines.push(format!("*...and {} more*", entries.len() - ENTRIES_SHOWN));
+            }
+            lines.join("\n")
+        },
+        None if playlists.is_empty() => "*No playlists*".to_owned(),
+        None => playlists.iter().map(|(name, entries)| format!("`{}` - {} entries", name, entries.len())).collect::<Vec<String>>().join("\n")
+    };
+    let _ = send_timed_reply(&ctx, description, Some(std::time::Duration::from_secs(30))).await;
+    Ok(())
+}
+
+// deletes a playlist
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn delete(ctx: Context<'_>, name: String, scope: Option<PlaylistScope>) -> Result<(), CommandError> {
+    let name = parse_name(&name)?;
+    let scope = scope.unwrap_or_default();
+    let owner_id = editable_owner_id(&ctx, scope).await?;
+
+    ctx.data().playlist_store.update(scope, owner_id, |playlists| playlists.remove(&name)).await?.ok_or(CommandError::PlaylistNotFound)?;
+    let _ = send_timed_reply(&ctx, format!("Deleted `{}`", name), None).await;
+    Ok(())
+}
+
+// adds a query to a playlist, creating it if it doesn't exist yet
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn add(ctx: Context<'_>, name: String, scope: Option<PlaylistScope>, query: Vec<String>) -> Result<(), CommandError> {
+    if query.is_empty() { return Err(CommandError::InvalidQuery); }
+    let query = query.join(" ");
+    let name = parse_name(&name)?;
+    let scope = scope.unwrap_or_default();
+    let owner_id = editable_owner_id(&ctx, scope).await?;
+
+    ctx.data().playlist_store.update(scope, owner_id, |playlists| playlists.entry(name.clone()).or_default().push(PlaylistEntry::Query(query))).await?;
+    let _ = send_timed_reply(&ctx, format!("Added to `{}`", name), None).await;
+    Ok(())
+}
+
+// enqueues the entries in order, returns how many tracks were added and how many entries couldn't be converted
+pub async fn enqueue_entries(ctx: &Context<'_>, handler: Arc<Mutex<Call>>, entries: Vec<PlaylistEntry>) -> (usize, usize) {
+    let guild_id = ctx.guild_id().unwrap().get();
+    let audio_settings = ctx.data().guild_states.with(guild_id, |state| state.settings.audio_settings.clone()).await;
+
+    let (mut added, mut skipped) = (0, 0);
+    for entry in entries {
+        match entry {
+            PlaylistEntry::Track(video_metadata) => {
+                // imported files can contain any link, they get the same check as links passed to /play
+                if let Some(url) = video_metadata.audio_source.url() {
+                    if crate::url_guard::check_url(url).await.is_err() {
+                        skipped += 1;
+                        continue;
+                    }
+                }
+                let track = track_from_metadata(TrackMetadata { video_metadata, added_by: ctx.author().into() }, ctx.data().reqwest_client.clone());
+                handler.lock().await.enqueue(process_track(track, &audio_settings)).await;
+                added += 1;
+            },
+            PlaylistEntry::Query(query) => match ctx.data().convert_query(&query, ctx.author().into()).await {
+                Ok(converted_query) => added += enqueue_converted_query(handler.clone(), converted_query, &audio_settings).await,
+                Err(_) => skipped += 1
+            }
+        }
+    }
+    (added, skipped)
+}
+
+fn parse_name(name: &str) -> Result<String, CommandError> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH { return Err(CommandError::InvalidPlaylistName); }
+    Ok(name)
+}
+
+fn owner_id(ctx: &Context<'_>, scope: PlaylistScope) -> u64 {
+    match scope {
+        PlaylistScope::Personal => ctx.author().id.get(),
+        PlaylistScope::Guild => ctx.guild_id().unwrap().get()
+    }
+}
+
+// server playlists can only be changed by djs
+async fn editable_owner_id(ctx: &Context<'_>, scope: PlaylistScope) -> Result<u64, CommandError> {
+    if scope == PlaylistScope::Guild {
+        let guild = ctx.guild().unwrap().clone();
+        if !has_permission(ctx, &guild, PermissionLevel::Dj).await { return Err(CommandError::MissingPermissions); }
+    }
+    Ok(owner_id(ctx, scope))
+}
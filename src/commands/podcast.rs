@@ -0,0 +1,39 @@
+use crate::{data::Context, convert_query::ConversionError, playlists::PlaylistEntry, scrapers::podcast};
+
+use crate::commands::{ error::CommandError, playlist::enqueue_entries, utils::{join_author_channel, send_timed_reply} };
+
+const EPISODES_SHOWN: usize = 10;
+
+// lists the latest episodes of a podcast feed, or queues one of them by its number
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn podcast(ctx: Context<'_>, feed: String, episode: Option<usize>) -> Result<(), CommandError> {
+    let guild = ctx.guild().unwrap().clone();
+    ctx.defer_ephemeral().await?; // feeds can be large
+    let episodes = podcast::feed(&ctx.data().reqwest_client, feed.trim()).await
+        .map_err(ConversionError::from)?
+        .ok_or(CommandError::InvalidQuery)?;
+
+    let Some(episode) = episode else {
+        let mut lines = episodes.iter().take(EPISODES_SHOWN).enumerate().map(|(index, episode)| {
+            let formatted_duration = episode.video_metadata.formatted_duration();
+            match &episode.published {
+                Some(published) => format!("{}. {} | {} | *{}*", index + 1, episode.title, formatted_duration, published),
+                None => format!("{}. {} | {}", index + 1, episode.title, formatted_duration)
+            }
+        }).collect::<Vec<String>>();
+        lines.push("Pass an episode number to play it".to_owned());
+        let _ = send_timed_reply(&ctx, lines.join("\n"), Some(std::time::Duration::from_secs(30))).await;
+        return Ok(());
+    };
+
+    let episode = episodes.into_iter().nth(episode.checked_sub(1).ok_or(CommandError::InvalidIndex)?).ok_or(CommandError::InvalidIndex)?;
+    // the feed decides where the audio is, so the link is checked before joining
+    if let Some(url) = episode.video_metadata.audio_source.url() {
+        crate::url_guard::check_url(url).await.map_err(|err| ConversionError::from(podcast::PodcastError::from(err)))?;
+    }
+    let handler = join_author_channel(&ctx, &guild).await?;
+    enqueue_entries(&ctx, handler, vec![PlaylistEntry::Track(episode.video_metadata)]).await;
+
+    let _ = send_timed_reply(&ctx, format!("Added {}", episode.title), None).await;
+    Ok(())
+}
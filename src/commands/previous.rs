@@ -0,0 +1,38 @@
+use crate::{data::Context, audio_processing::process_track, metadata::{rebuild_track, track_from_metadata}};
+
+use crate::commands::{ error::{VoiceError, CommandError}, utils::same_voice_channel, play::{insert, Insertion} };
+
+// plays the previously played track, the current track gets queued right after it
+#[poise::command(slash_command, prefix_command, guild_only, aliases("prev", "back"))]
+pub async fn previous(ctx: Context<'_>) -> Result<(), CommandError> {
+    let guild = ctx.guild().unwrap().clone();
+    let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
+
+    if let Some(handler) = manager.get(guild.id) {
+        if !same_voice_channel(&guild, &ctx.author().id, handler.clone()).await { return Ok(()); }
+
+        let current_track = handler.lock().await.queue().current();
+        let previous_track_metadata = ctx.data().guild_states.with(guild.id.get(), |state| {
+            // the last entry belongs to the current track, it's added back once it starts playing again
+            if current_track.is_some() { state.history.pop_back(); }
+            state.history.pop_back()
+        }).await.ok_or(CommandError::EmptyHistory)?;
+
+        let client = ctx.data().reqwest_client.clone();
+        let mut tracks = vec![track_from_metadata(previous_track_metadata, client.clone())];
+        if let Some(current_track) = &current_track {
+            tracks.extend(rebuild_track(current_track, client).await);
+        }
+
+        let count = tracks.len();
+        let audio_settings = ctx.data().guild_states.with(guild.id.get(), |state| state.settings.audio_settings.clone()).await;
+        {
+            let mut handler_guard = handler.lock().await;
+            for track in tracks {
+                handler_guard.enqueue(process_track(track, &audio_settings)).await;
+            }
+        }
+        insert(handler, count, Insertion::Now).await?;
+    }
+    Ok(())
+}
@@ -1,14 +1,14 @@
-use crate::{data::Context, metadata::LazyMetadata};
+use crate::{data::Context, metadata::LazyMetadata, guild_state::GuildStates};
 use poise::{serenity_prelude::{ReactionType, ComponentInteraction}, ReplyHandle, CreateReply};
 use serenity::{ builder::{ CreateEmbed, CreateActionRow, CreateEmbedFooter, CreateButton, CreateAllowedMentions }, model::Color };
 use std::{ sync::Arc, time::Duration };
 use tokio::sync::Mutex;
-use songbird::{ Call, tracks::LoopState };
+use songbird::Call;
 use crate::commands::error::{VoiceError, CommandError};
 use futures::stream::*;
 
-const TRACKS_PER_PAGE: usize = 7;
-const CHARACTERS_PER_FIELD_LINE: usize = 1024 / TRACKS_PER_PAGE - 3; // -3 to account enumeration formatting and a new line
+pub const TRACKS_PER_PAGE: usize = 7;
+pub const CHARACTERS_PER_FIELD_LINE: usize = 1024 / TRACKS_PER_PAGE - 3; // -3 to account enumeration formatting and a new line
 
 // shows the queue
 #[poise::command(slash_command, prefix_command, guild_only, ephemeral, aliases("q"))]
@@ -20,7 +20,8 @@ pub async fn queue(ctx: Context<'_>, page: Option<usize>) -> Result<(), CommandE
     let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
 
     if let Some(handler) = manager.get(guild.id) {
-        let (queue_embed, mut last_page) = assemble_embed(handler.clone(), page).await;
+        let guild_states = &ctx.data().guild_states;
+        let (queue_embed, mut last_page) = assemble_embed(handler.clone(), page, guild_states, guild.id.get()).await;
         let reply_handle = ctx.send(CreateReply::default()
             .reply(true)
             .allowed_mentions(CreateAllowedMentions::new()
@@ -38,14 +39,14 @@ pub async fn queue(ctx: Context<'_>, page: Option<usize>) -> Result<(), CommandE
             match message_collector.data.custom_id.as_str() { // ?? ignore error or return
                 "prev" => {
                     page -= 1;
-                    update_queue_embed(page, &mut last_page, ctx, handler.clone(), &reply_handle, message_collector).await?;
+                    update_queue_embed(page, &mut last_page, ctx, handler.clone(), guild_states, guild.id.get(), &reply_handle, message_collector).await?;
                 },
                 "next" => {
                     page += 1;
-                    update_queue_embed(page, &mut last_page, ctx, handler.clone(), &reply_handle, message_collector).await?;
+                    update_queue_embed(page, &mut last_page, ctx, handler.clone(), guild_states, guild.id.get(), &reply_handle, message_collector).await?;
                 },
                 "reload" => {
-                    update_queue_embed(page, &mut last_page, ctx, handler.clone(), &reply_handle, message_collector).await?;
+                    update_queue_embed(page, &mut last_page, ctx, handler.clone(), guild_states, guild.id.get(), &reply_handle, message_collector).await?;
                 }
                 _ => ()
             }
@@ -70,11 +71,11 @@ async fn search_burst(handler: Arc<Mutex<Call>>, page: usize) {
     }
 }
 
-pub fn create_queue_embed(stringified_metadatas: Vec<String>, page: usize, last_page: usize, queue_len: usize, looping: bool) -> CreateEmbed {
+pub fn create_queue_embed(stringified_metadatas: Vec<String>, page: usize, last_page: usize, queue_len: usize, loop_description: String) -> CreateEmbed {
     let mut embed = CreateEmbed::default()
         .color(Color::PURPLE)
         .title("Queue")
-        .footer(CreateEmbedFooter::new(format!("Page: {}/{}  Tracks: {}   looping: {}", page + 1, last_page.max(1), queue_len, looping)));
+        .footer(CreateEmbedFooter::new(format!("Page: {}/{}  Tracks: {}   {}", page + 1, last_page.max(1), queue_len, loop_description)));
 
     let mut next_up = String::new();
     embed = embed.field("Currently Playing:", stringified_metadatas.first().unwrap_or(&"*Nothing*".to_owned()), false);
@@ -91,7 +92,7 @@ pub fn create_queue_embed(stringified_metadatas: Vec<String>, page: usize, last_
     embed
 }
 
-async fn assemble_embed(handler: Arc<Mutex<Call>>, page: usize) -> (CreateEmbed, usize) {
+async fn assemble_embed(handler: Arc<Mutex<Call>>, page: usize, guild_states: &GuildStates, guild_id: u64) -> (CreateEmbed, usize) {
     search_burst(handler.clone(), page).await;
 
     let handler_guard = handler.lock().await;
@@ -101,18 +102,15 @@ async fn assemble_embed(handler: Arc<Mutex<Call>>, page: usize) -> (CreateEmbed,
     drop(handler_guard);
 
     let mut stringified_metadatas: Vec<String> = vec![];
-    let mut looping = false;
+    let mut loops = None;
 
     // assemble the currently playing field
     if let Some(current_track) = current_track {
         let track_metadata = current_track.read_lazy_metadata().await.unwrap_or_default();
         let playtime = match current_track.get_info().await {
             Ok(info) => {
-                match info.loops{
-                    LoopState::Infinite => looping = true,
-                    _ => ()
-                }
-                Some(info.play_time)
+                loops = Some(info.loops);
+                Some(info.position)
             },
             Err(_) => None
         };
@@ -133,7 +131,8 @@ async fn assemble_embed(handler: Arc<Mutex<Call>>, page: usize) -> (CreateEmbed,
         stringified_metadatas.push(stringified_metadata);
     }
     let last_page = ((queue_len.max(1) - 1) as f32 / TRACKS_PER_PAGE as f32).ceil() as usize;
-    (create_queue_embed(stringified_metadatas, page, last_page, queue_len, looping), last_page)
+    let loop_mode = guild_states.with(guild_id, |state| state.loop_mode).await;
+    (create_queue_embed(stringified_metadatas, page, last_page, queue_len, loop_mode.describe(loops)), last_page)
 }
 
 pub fn create_buttons(page: usize, last_page: usize) -> CreateActionRow {
@@ -144,8 +143,8 @@ pub fn create_buttons(page: usize, last_page: usize) -> CreateActionRow {
     ])
 }
 
-pub async fn update_queue_embed<'a>(page: usize, last_page: &mut usize, ctx: Context<'a>, handler: Arc<Mutex<Call>>, reply_handle: &ReplyHandle<'a>, message_collector: ComponentInteraction) -> Result<(), CommandError> {
-    let (new_queue_embed, new_last_page) = assemble_embed(handler, page).await;
+pub async fn update_queue_embed<'a>(page: usize, last_page: &mut usize, ctx: Context<'a>, handler: Arc<Mutex<Call>>, guild_states: &GuildStates, guild_id: u64, reply_handle: &ReplyHandle<'a>, message_collector: ComponentInteraction) -> Result<(), CommandError> {
+    let (new_queue_embed, new_last_page) = assemble_embed(handler, page, guild_states, guild_id).await;
     *last_page = new_last_page;
     let _ = reply_handle.edit(ctx.clone(), CreateReply::default().embed(new_queue_embed).components(vec![create_buttons(page, *last_page)])).await?;
     let _ = message_collector.defer(ctx).await;
@@ -0,0 +1,60 @@
+use crate::data::Context;
+
+use crate::commands::{ error::{VoiceError, CommandError}, utils::{same_voice_channel, send_timed_reply} };
+
+// removes a track or a range of tracks ("3" or "3-7") from the queue, numbered as in the queue command
+#[poise::command(slash_command, prefix_command, guild_only, aliases("rm"))]
+pub async fn remove(ctx: Context<'_>, tracks: String) -> Result<(), CommandError> {
+    let guild = ctx.guild().unwrap().clone();
+    let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
+
+    let (start, end) = parse_range(&tracks).ok_or(CommandError::InvalidIndex)?;
+
+    if let Some(handler) = manager.get(guild.id) {
+        if !same_voice_channel(&guild, &ctx.author().id, handler.clone()).await { return Ok(()); }
+
+        let removed = handler.lock().await.queue().modify_queue(|queue| {
+            if end >= queue.len() { return None; }
+            Some(queue.drain(start..=end).collect::<Vec<_>>())
+        }).ok_or(CommandError::InvalidIndex)?;
+
+        for track in removed.iter() {
+            let _ = track.stop();
+        }
+        let _ = send_timed_reply(&ctx, format!("Removed {} tracks", removed.len()), None).await;
+    }
+    Ok(())
+}
+
+// parses "n" or "n-m" into an inclusive range of queue indices, the current track (0) can't be removed
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) => (start.trim().parse::<usize>().ok()?, end.trim().parse::<usize>().ok()?),
+        None => {
+            let index = range.trim().parse::<usize>().ok()?;
+            (index, index)
+        }
+    };
+    if start == 0 || start > end { return None; }
+    Some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(parse_range("3"), Some((3, 3)));
+        assert_eq!(parse_range("2-5"), Some((2, 5)));
+        assert_eq!(parse_range(" 2 - 5 "), Some((2, 5)));
+        assert_eq!(parse_range("4-4"), Some((4, 4)));
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        for range in ["0", "0-3", "5-2", "", "a-b", "1-", "-3", "1-2-3"] {
+            assert_eq!(parse_range(range), None, "{}", range);
+        }
+    }
+}
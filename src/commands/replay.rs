@@ -0,0 +1,18 @@
+use crate::data::Context;
+
+use crate::commands::{ error::{VoiceError, CommandError}, utils::same_voice_channel };
+
+// restarts the current track
+#[poise::command(slash_command, prefix_command, guild_only, aliases("restart"))]
+pub async fn replay(ctx: Context<'_>) -> Result<(), CommandError> {
+    let guild = ctx.guild().unwrap().clone();
+    let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
+
+    if let Some(handler) = manager.get(guild.id) {
+        if !same_voice_channel(&guild, &ctx.author().id, handler.clone()).await { return Ok(()); }
+
+        let Some(current_track) = handler.lock().await.queue().current() else { return Ok(()); };
+        current_track.seek_async(std::time::Duration::ZERO).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use crate::{data::Context, convert_query::{ConversionError, ConvertedQuery, MetaInput, YouTubeComposer}, metadata::TrackMetadata, scrapers::youtube::search_results, utils::format_duration};
+use poise::CreateReply;
+use serenity::{all::ComponentInteractionDataKind, builder::{CreateActionRow, CreateEmbed, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption}, model::Color};
+use crate::commands::{ error::CommandError, play::{add_converted_query, Insertion}, utils::join_author_channel };
+
+const RESULTS_SHOWN: usize = 10;
+const PICK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+// discord rejects longer option labels and descriptions
+const MAX_OPTION_LENGTH: usize = 100;
+
+// shows the top youtube results for a query and queues the ones picked from the menu
+#[poise::command(slash_command, prefix_command, guild_only, aliases("find"))]
+pub async fn search(ctx: Context<'_>, query: Vec<String>) -> Result<(), CommandError> {
+    if query.is_empty() { return Err(CommandError::InvalidQuery) }
+    let query = query.join(" "); // represent query as a string vector so spaces are allowed
+    let guild = ctx.guild().unwrap().clone();
+
+    ctx.defer().await?;
+    let results = search_results(&query, RESULTS_SHOWN).await.map_err(ConversionError::from)?;
+    if results.is_empty() { return Err(CommandError::NoResults) }
+
+    let options = results.iter().enumerate().map(|(index, result)| {
+        let video_metadata = &result.video_metadata;
+        let description = format!("{} | {}", result.channel, format_duration(video_metadata.duration, None));
+        CreateSelectMenuOption::new(truncate(&video_metadata.title), index.to_string()).description(truncate(&description))
+    }).collect::<Vec<CreateSelectMenuOption>>();
+    let select_menu = CreateSelectMenu::new("search", CreateSelectMenuKind::String { options })
+        .placeholder("Pick the tracks to queue")
+        .min_values(1)
+        .max_values(results.len() as u8);
+
+    let reply_handle = ctx.send(CreateReply::default()
+        .reply(true)
+        .embed(CreateEmbed::new().title("Search Results:").description(format!("Results for `{}`", query)).color(Color::PURPLE))
+        .components(vec![CreateActionRow::SelectMenu(select_menu)])
+    ).await?;
+    let message = reply_handle.message().await?;
+
+    let interaction = message.await_component_interaction(ctx)
+        .author_id(ctx.author().id)
+        .timeout(PICK_TIMEOUT)
+        .await;
+    if let Some(interaction) = &interaction {
+        let _ = interaction.defer(ctx).await;
+    }
+    let _ = reply_handle.delete(ctx).await;
+
+    let Some(interaction) = interaction else { return Ok(()); };
+    let ComponentInteractionDataKind::StringSelect { values } = &interaction.data.kind else { return Ok(()); };
+    let client = ctx.data().reqwest_client.clone();
+    let mut metainputs = values.iter()
+        .filter_map(|value| value.parse::<usize>().ok())
+        .filter_map(|index| results.get(index))
+        .map(|result| {
+            let video_metadata = result.video_metadata.clone();
+            let input = YouTubeComposer::Metadata { metadata: video_metadata.clone(), client: client.clone() }.into();
+            let track_metadata = TrackMetadata { video_metadata, added_by: ctx.author().into() };
+            MetaInput { input, track_metadata, start: None }
+        })
+        .collect::<Vec<MetaInput>>();
+    if metainputs.is_empty() { return Ok(()); }
+
+    let handler = join_author_channel(&ctx, &guild).await?;
+    let was_empty = handler.lock().await.queue().is_empty();
+    let converted_query = match metainputs.len() {
+        1 => ConvertedQuery::LiveVideo(metainputs.remove(0)),
+        _ => ConvertedQuery::LivePlaylist(metainputs, 0)
+    };
+    add_converted_query(ctx, handler, converted_query, was_empty, Insertion::Back).await
+}
+
+fn truncate(text: &str) -> String {
+    match text.chars().count() > MAX_OPTION_LENGTH {
+        true => format!("{}…", text.chars().take(MAX_OPTION_LENGTH - 1).collect::<String>()),
+        false => text.to_owned()
+    }
+}
@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use crate::{data::Context, metadata::{AudioSource, LazyMetadata}, utils::{format_duration, parse_timestamp}};
+use crate::commands::{ error::{VoiceError, CommandError}, utils::{same_voice_channel, send_timed_reply} };
+
+// seeks to a position in the current track, accepts absolute ("1:23") and relative ("+30", "-10") positions
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn seek(ctx: Context<'_>, position: String) -> Result<(), CommandError> {
+    let guild = ctx.guild().unwrap().clone();
+    let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
+
+    if let Some(handler) = manager.get(guild.id) {
+        if !same_voice_channel(&guild, &ctx.author().id, handler.clone()).await { return Ok(()); }
+
+        let Some(mut current_track) = handler.lock().await.queue().current() else { return Ok(()); };
+        let track_metadata = current_track.read_generate_lazy_metadata().await?;
+        if let AudioSource::Jeja { .. } | AudioSource::Radio { .. } = track_metadata.video_metadata.audio_source { return Err(CommandError::Unseekable); }
+
+        let info = current_track.get_info().await?;
+        let position = position.trim();
+        let target = if let Some(offset) = position.strip_prefix('+') {
+            info.position + parse_timestamp(offset).ok_or(CommandError::InvalidTimestamp)?
+        } else if let Some(offset) = position.strip_prefix('-') {
+            info.position.saturating_sub(parse_timestamp(offset).ok_or(CommandError::InvalidTimestamp)?)
+        } else {
+            parse_timestamp(position).ok_or(CommandError::InvalidTimestamp)?
+        };
+
+        // seeking past the end would just end the track, skip should be used for that
+        let duration = track_metadata.video_metadata.duration;
+        let target = if duration.is_zero() { target } else { target.min(duration.saturating_sub(Duration::from_secs(1))) };
+
+        let position = current_track.seek_async(target).await?;
+        let _ = send_timed_reply(&ctx, format!("Seeked to {}", format_duration(position, None)), None).await;
+    }
+    Ok(())
+}
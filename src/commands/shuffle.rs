@@ -0,0 +1,23 @@
+use crate::data::Context;
+use rand::seq::SliceRandom;
+
+use crate::commands::{ error::{VoiceError, CommandError}, utils::{same_voice_channel, send_timed_reply} };
+
+// shuffles the queue, the current track stays in place
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn shuffle(ctx: Context<'_>) -> Result<(), CommandError> {
+    let guild = ctx.guild().unwrap().clone();
+    let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
+
+    if let Some(handler) = manager.get(guild.id) {
+        if !same_voice_channel(&guild, &ctx.author().id, handler.clone()).await { return Ok(()); }
+
+        let shuffled = handler.lock().await.queue().modify_queue(|queue| {
+            let Some((_, next_up)) = queue.make_contiguous().split_first_mut() else { return 0; };
+            next_up.shuffle(&mut rand::thread_rng());
+            next_up.len()
+        });
+        let _ = send_timed_reply(&ctx, format!("Shuffled {} tracks", shuffled), None).await;
+    }
+    Ok(())
+}
@@ -1,8 +1,26 @@
-use crate::{data::Context, commands::error::CommandError};
+use std::{collections::HashSet, sync::Arc};
 
-use crate::commands::{ error::VoiceError, utils::same_voice_channel };
+use crate::{data::Context, commands::error::CommandError, metadata::LazyMetadata};
+use poise::serenity_prelude::Guild;
+use songbird::{Call, tracks::TrackHandle};
+use tokio::sync::{Mutex, RwLock};
+use typemap::{Key as TypeMapKey, ShareMap};
 
-// skips to the next track
+use crate::commands::{ error::VoiceError, utils::{same_voice_channel, send_timed_reply, count_listeners, is_dj} };
+
+// ids of users who voted to skip a track, kept in the track's data so they're gone once it ends
+pub struct SkipVotes;
+
+impl TypeMapKey for SkipVotes {
+    type Value = HashSet<u64>;
+}
+
+pub enum SkipOutcome {
+    Skipped,
+    Voted { votes: usize, required: usize }
+}
+
+// skips to the next track, tracks added by someone else need enough votes
 #[poise::command(slash_command, prefix_command, guild_only, aliases("next"))]
 pub async fn skip(ctx: Context<'_>) -> Result<(), CommandError> {
     let guild = ctx.guild().unwrap().clone();
@@ -11,7 +29,52 @@ pub async fn skip(ctx: Context<'_>) -> Result<(), CommandError> {
     if let Some(handler) = manager.get(guild.id) {
         if !same_voice_channel(&guild, &ctx.author().id, handler.clone()).await { return Ok(()); }
         
-        handler.lock().await.queue().skip()?;
+        if let SkipOutcome::Voted { votes, required } = vote_skip(&ctx, &guild, handler).await? {
+            let _ = send_timed_reply(&ctx, format!("Voted to skip ({}/{})", votes, required), None).await;
+        }
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+// the requester and djs skip right away, everyone else votes until enough listeners agreed
+pub async fn vote_skip(ctx: &Context<'_>, guild: &Guild, handler: Arc<Mutex<Call>>) -> Result<SkipOutcome, CommandError> {
+    let Some(current_track) = handler.lock().await.queue().current() else { return Ok(SkipOutcome::Skipped); };
+
+    if requester_id(&current_track).await != Some(ctx.author().id.get()) && needs_vote(ctx, guild).await {
+        let (votes, required) = add_vote::<SkipVotes>(ctx, guild, &current_track).await;
+        if votes < required { return Ok(SkipOutcome::Voted { votes, required }); }
+    }
+
+    handler.lock().await.queue().skip()?;
+    Ok(SkipOutcome::Skipped)
+}
+
+pub async fn requester_id(track_handle: &TrackHandle) -> Option<u64> {
+    match track_handle.read_lazy_metadata().await {
+        Some(track_metadata) => Some(track_metadata.added_by.id),
+        None => track_handle.read_added_by().await.map(|added_by| added_by.id)
+    }
+}
+
+// djs never have to vote, and neither does anyone if voting is turned off
+pub async fn needs_vote(ctx: &Context<'_>, guild: &Guild) -> bool {
+    let vote_skip_ratio = ctx.data().guild_states.with(guild.id.get(), |state| state.settings.vote_skip_ratio).await;
+    vote_skip_ratio > 0.0 && !is_dj(ctx, guild).await
+}
+
+// records the invoker's vote on the track, returns the number of votes and how many are required
+pub async fn add_vote<K: TypeMapKey<Value = HashSet<u64>>>(ctx: &Context<'_>, guild: &Guild, track_handle: &TrackHandle) -> (usize, usize) {
+    let vote_skip_ratio = ctx.data().guild_states.with(guild.id.get(), |state| state.settings.vote_skip_ratio).await;
+    let bot_channel_id = guild.voice_states.get(&ctx.framework().bot_id).and_then(|voice_state| voice_state.channel_id);
+    let listeners = match bot_channel_id {
+        Some(channel_id) => count_listeners(ctx, channel_id).await.unwrap_or(1),
+        None => 1
+    };
+    let required = ((listeners as f32 * vote_skip_ratio).ceil() as usize).max(1);
+
+    let data = track_handle.data::<RwLock<ShareMap>>();
+    let mut data_guard = data.write().await;
+    let votes = data_guard.entry::<K>().or_insert_with(HashSet::new);
+    votes.insert(ctx.author().id.get());
+    (votes.len(), required)
+}
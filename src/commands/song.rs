@@ -1,12 +1,12 @@
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError};
 
-use crate::{data::Context, metadata::{LazyMetadata, TrackMetadata}, utils::format_duration};
-use poise::{CreateReply, serenity_prelude::{ReactionType, ComponentInteraction}, ReplyHandle};
-use serenity::builder::{CreateEmbed, CreateActionRow, CreateAllowedMentions, CreateButton, CreateEmbedAuthor, CreateEmbedFooter};
+use crate::{data::Context, audio_processing::FilterPreset, metadata::{LazyMetadata, TrackMetadata}, utils::format_duration, guild_state::GuildStates};
+use poise::{ChoiceParameter, CreateReply, serenity_prelude::{ReactionType, ComponentInteraction}, ReplyHandle};
+use serenity::builder::{CreateEmbed, CreateActionRow, CreateAllowedMentions, CreateButton, CreateEmbedAuthor, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage};
 use futures::stream::*;
-use songbird::{Call, tracks::LoopState};
+use songbird::Call;
 use tokio::sync::Mutex;
-use crate::commands::{ error::{VoiceError, CommandError}, utils::same_voice_channel };
+use crate::commands::{ error::{VoiceError, CommandError}, utils::same_voice_channel, _loop::set_loop_mode, skip::{vote_skip, SkipOutcome} };
 
 // shows the currently playing track
 #[poise::command(slash_command, prefix_command, guild_only, ephemeral, aliases("s"))]
@@ -14,7 +14,8 @@ pub async fn song(ctx: Context<'_>) -> Result<(), CommandError> {
     let guild = ctx.guild().unwrap().clone();
     let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
     if let Some(handler) = manager.get(guild.id) {
-        let currently_playing_msg = create_currently_playing_message(handler.clone()).await?;
+        let guild_states = &ctx.data().guild_states;
+        let currently_playing_msg = create_currently_playing_message(handler.clone(), guild_states, guild.id.get()).await?;
         let reply_handle = ctx.send(currently_playing_msg).await?;
 
         let message = reply_handle.message().await?;
@@ -30,24 +31,29 @@ pub async fn song(ctx: Context<'_>) -> Result<(), CommandError> {
             match message_collector.data.custom_id.as_str() {
                 "skip" => {
                     if same_voice_channel(&guild, &ctx.author().id, handler.clone()).await {
-                        handler.lock().await.queue().skip()?;
-                        tokio::time::sleep(std::time::Duration::from_millis(100)).await; // waits for the queue to update
-                        update_currently_playing_message(message_collector, &ctx, handler.clone(), &reply_handle).await?;
+                        match vote_skip(&ctx, &guild, handler.clone()).await? {
+                            SkipOutcome::Skipped => {
+                                tokio::time::sleep(std::time::Duration::from_millis(100)).await; // waits for the queue to update
+                                update_currently_playing_message(message_collector, &ctx, handler.clone(), guild_states, guild.id.get(), &reply_handle).await?;
+                            },
+                            SkipOutcome::Voted { votes, required } => {
+                                let _ = message_collector.create_response(&ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                                    .content(format!("Voted to skip ({}/{})", votes, required))
+                                    .ephemeral(true))).await;
+                            }
+                        }
                     }
                 },
                 "loop" => {
-                    let current_track_handle = handler.lock().await.queue().current();
-                    if let Some(current_track_handle) = current_track_handle {
-                        let info = current_track_handle.get_info().await?;
-                        match info.loops {
-                            LoopState::Infinite => current_track_handle.disable_loop()?,
-                            LoopState::Finite(_) => current_track_handle.enable_loop()?
-                        }
+                    if same_voice_channel(&guild, &ctx.author().id, handler.clone()).await {
+                        let current_track_handle = handler.lock().await.queue().current();
+                        let loop_mode = guild_states.with(guild.id.get(), |state| state.loop_mode).await.next();
+                        set_loop_mode(guild_states, guild.id.get(), current_track_handle, loop_mode, None).await?;
                     }
-                    update_currently_playing_message(message_collector, &ctx, handler.clone(), &reply_handle).await?;
+                    update_currently_playing_message(message_collector, &ctx, handler.clone(), guild_states, guild.id.get(), &reply_handle).await?;
                 },
                 "refresh" => {
-                    update_currently_playing_message(message_collector, &ctx, handler.clone(), &reply_handle).await?;
+                    update_currently_playing_message(message_collector, &ctx, handler.clone(), guild_states, guild.id.get(), &reply_handle).await?;
                 },
                 _ => ()
             }
@@ -57,15 +63,17 @@ pub async fn song(ctx: Context<'_>) -> Result<(), CommandError> {
     Ok(())
 }
 
-async fn update_currently_playing_message<'a>(message_collector: ComponentInteraction, ctx: &Context<'a>, handler: Arc<Mutex<Call>>, reply_handle: &ReplyHandle<'a>) -> Result<(), CommandError> {
-    let edit = create_currently_playing_message(handler).await?;
+async fn update_currently_playing_message<'a>(message_collector: ComponentInteraction, ctx: &Context<'a>, handler: Arc<Mutex<Call>>, guild_states: &GuildStates, guild_id: u64, reply_handle: &ReplyHandle<'a>) -> Result<(), CommandError> {
+    let edit = create_currently_playing_message(handler, guild_states, guild_id).await?;
     reply_handle.edit(ctx.clone(), edit).await?;
     let _ = message_collector.defer(ctx).await;
     Ok(())
 }
 
-async fn create_currently_playing_message<'a>(handler: Arc<Mutex<Call>>) -> Result<CreateReply, CommandError> {
+async fn create_currently_playing_message<'a>(handler: Arc<Mutex<Call>>, guild_states: &GuildStates, guild_id: u64) -> Result<CreateReply, CommandError> {
     let mut currently_playing_msg = CreateReply::default().reply(true).allowed_mentions(CreateAllowedMentions::new().replied_user(true));
+    let (loop_mode, volume, audio_settings) = guild_states.with(guild_id, |state| (state.loop_mode, state.settings.volume, state.settings.audio_settings.clone())).await;
+    let filter = audio_settings.read().unwrap_or_else(PoisonError::into_inner).filter;
 
     let current_track_handle = handler.lock().await.queue().current(); // mutex dropped immediately
     match current_track_handle {
@@ -73,18 +81,13 @@ async fn create_currently_playing_message<'a>(handler: Arc<Mutex<Call>>) -> Resu
             let track_metadata = current_track_handle.read_generate_lazy_metadata().await?;
             match current_track_handle.get_info().await {
                 Ok(info) => {
-                    let looping = match info.loops {
-                        LoopState::Infinite => true,
-                        LoopState::Finite(_) => false
-                    };
-
                     currently_playing_msg = currently_playing_msg
-                        .embed(create_currently_playing_embed(track_metadata, info.play_time, looping))
+                        .embed(create_currently_playing_embed(track_metadata, info.position, loop_mode.describe(Some(info.loops)), info.volume, filter))
                         .components(vec![create_buttons()]);
                 },
                 Err(_) => {
                     currently_playing_msg = currently_playing_msg
-                        .embed(create_currently_playing_embed(track_metadata, std::time::Duration::ZERO, false))
+                        .embed(create_currently_playing_embed(track_metadata, std::time::Duration::ZERO, loop_mode.describe(None), volume, filter))
                         .components(vec![create_buttons()]);
                 }
             }
@@ -94,7 +97,7 @@ async fn create_currently_playing_message<'a>(handler: Arc<Mutex<Call>>) -> Resu
                 .embed(CreateEmbed::new()
                     .title("Currently Playing:")
                     .description("*Nothing*")
-                    .footer(CreateEmbedFooter::new("looping: false")))
+                    .footer(CreateEmbedFooter::new(create_footer_text(loop_mode.describe(None), volume, filter))))
                 .components(vec![create_buttons()]);
         }
     }
@@ -109,7 +112,7 @@ fn create_buttons() -> CreateActionRow {
     ])
 }
 
-pub fn create_currently_playing_embed(track_metadata: TrackMetadata, playtime: std::time::Duration, looping: bool) -> CreateEmbed {
+pub fn create_currently_playing_embed(track_metadata: TrackMetadata, playtime: std::time::Duration, loop_description: String, volume: f32, filter: FilterPreset) -> CreateEmbed {
     let TrackMetadata { added_by, video_metadata } = track_metadata;
     let duration_string = format_duration(video_metadata.duration, None);
     let playtime_string = format_duration(playtime, Some(duration_string.len()));
@@ -123,6 +126,12 @@ pub fn create_currently_playing_embed(track_metadata: TrackMetadata, playtime: s
         crate::metadata::AudioSource::YouTube { video_id } => {
             format!("[{}](https://youtu.be/{}) | {} / {}", video_metadata.title, video_id, playtime_string, duration_string)
         },
+        crate::metadata::AudioSource::Url { url } | crate::metadata::AudioSource::SoundCloud { url, .. } | crate::metadata::AudioSource::Bandcamp { url } => {
+            format!("[{}]({}) | {} / {}", video_metadata.title, url, playtime_string, duration_string)
+        },
+        crate::metadata::AudioSource::Radio { ref url, .. } => {
+            format!("[{}]({}) | {}", video_metadata.display_title(), url, video_metadata.formatted_duration())
+        },
         crate::metadata::AudioSource::Jeja { .. } => video_metadata.title.clone()
     })
     .author({
@@ -133,5 +142,13 @@ pub fn create_currently_playing_embed(track_metadata: TrackMetadata, playtime: s
         }
         author
     })
-    .footer(CreateEmbedFooter::new(format!("looping: {}", looping)))
+    .footer(CreateEmbedFooter::new(create_footer_text(loop_description, volume, filter)))
+}
+
+fn create_footer_text(loop_description: String, volume: f32, filter: FilterPreset) -> String {
+    let mut footer_text = format!("{}   volume: {}%", loop_description, (volume * 100.0).round());
+    if filter != FilterPreset::Off {
+        footer_text.push_str(&format!("   filter: {}", filter.name()));
+    }
+    footer_text
 }
\ No newline at end of file
@@ -1,7 +1,17 @@
+use std::collections::HashSet;
+
 use crate::data::Context;
-use crate::commands::{ error::{VoiceError, CommandError}, utils::same_voice_channel };
+use crate::commands::{ error::{VoiceError, CommandError}, skip::{add_vote, needs_vote, requester_id}, utils::{same_voice_channel, send_timed_reply} };
+use typemap::Key as TypeMapKey;
+
+// ids of users who voted to stop, kept in the current track's data like skip votes
+pub struct StopVotes;
+
+impl TypeMapKey for StopVotes {
+    type Value = HashSet<u64>;
+}
 
-// stops the playback and clears the queue
+// stops the playback and clears the queue, a queue with other people's tracks needs enough votes like skipping does
 #[poise::command(slash_command, prefix_command, guild_only)]
 pub async fn stop(ctx: Context<'_>) -> Result<(), CommandError> {
     let guild = ctx.guild().unwrap().clone();
@@ -9,8 +19,27 @@ pub async fn stop(ctx: Context<'_>) -> Result<(), CommandError> {
     
     if let Some(handler) = manager.get(guild.id) {
         if !same_voice_channel(&guild, &ctx.author().id, handler.clone()).await { return Ok(()); }
-        
+
+        let track_handles = handler.lock().await.queue().current_queue();
+        let mut only_own_tracks = true;
+        for track_handle in &track_handles {
+            if requester_id(track_handle).await != Some(ctx.author().id.get()) {
+                only_own_tracks = false;
+                break;
+            }
+        }
+
+        if let (false, Some(current_track)) = (only_own_tracks, track_handles.first()) {
+            if needs_vote(&ctx, &guild).await {
+                let (votes, required) = add_vote::<StopVotes>(&ctx, &guild, current_track).await;
+                if votes < required {
+                    let _ = send_timed_reply(&ctx, format!("Voted to stop ({}/{})", votes, required), None).await;
+                    return Ok(());
+                }
+            }
+        }
+
         handler.lock().await.queue().stop();
     }
     Ok(())
-}
\ No newline at end of file
+}
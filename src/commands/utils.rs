@@ -1,10 +1,10 @@
 #![allow(dead_code)]
 
 use std::sync::Arc;
-use crate::{data::Context, error::DynError};
-use poise::{serenity_prelude::{Guild, UserId}, CreateReply};
-use serenity::{model::Color, builder::{CreateAllowedMentions, CreateEmbed}};
-use songbird::Call;
+use crate::{data::Context, error::DynError, metadata::LazyMetadataEventHandler, commands::{_loop::QueueLoopEventHandler, error::{CommandError, VoiceError}}, guild_state::GuildStates, permissions::{member_permission_level, PermissionLevel}, queue_persistence::{QueueStore, QueuePersistenceEventHandler}};
+use poise::{serenity_prelude::{ChannelId, Guild, UserId}, CreateReply};
+use serenity::{model::Color, builder::{CreateAllowedMentions, CreateEmbed}, http::Http};
+use songbird::{Call, Event, TrackEvent};
 use tokio::sync::Mutex;
 
 pub async fn send_timed_reply<S: Into<String>>(ctx: &Context<'_>, description: S, delay: Option<std::time::Duration>) -> Result<(), DynError> {
@@ -18,7 +18,11 @@ pub async fn send_timed_reply<S: Into<String>>(ctx: &Context<'_>, description: S
             .description(description)
             .color(Color::PURPLE))
     ).await?;
-    ctx.data().add_to_cleanup(reply_handle, delay.unwrap_or(std::time::Duration::from_secs(5))).await;
+    let delay = match delay {
+        Some(delay) => delay,
+        None => reply_cleanup_delay(ctx).await
+    };
+    ctx.data().add_to_cleanup(reply_handle, delay).await;
     Ok(())
 
     
@@ -36,10 +40,24 @@ pub async fn send_timed_error<S: Into<String>>(ctx: &Context<'_>, description: S
             .description(description)
             .color(Color::from_rgb(255, 0, 0)))
     ).await?;
-    ctx.data().add_to_cleanup(reply_handle, delay.unwrap_or(std::time::Duration::from_secs(5))).await;
+    let delay = match delay {
+        Some(delay) => delay,
+        None => reply_cleanup_delay(ctx).await
+    };
+    ctx.data().add_to_cleanup(reply_handle, delay).await;
     Ok(())
 }
 
+pub async fn reply_cleanup_delay(ctx: &Context<'_>) -> std::time::Duration {
+    let guild_id = ctx.guild_id().map(|guild_id| guild_id.get()).unwrap_or(0);
+    ctx.data().guild_states.with(guild_id, |state| state.settings.reply_cleanup_delay()).await
+}
+
+pub async fn announcement_cleanup_delay(ctx: &Context<'_>) -> std::time::Duration {
+    let guild_id = ctx.guild_id().map(|guild_id| guild_id.get()).unwrap_or(0);
+    ctx.data().guild_states.with(guild_id, |state| state.settings.announcement_cleanup_delay()).await
+}
+
 pub async fn same_voice_channel(guild: &Guild, user_id: &UserId, handler: Arc<Mutex<Call>>) -> bool {
     // user voice info
     let Some(user_voice) = guild.voice_states.get(user_id) else {
@@ -74,29 +92,117 @@ pub async fn should_move_channels(ctx: &Context<'_>, guild: &Guild, user_voice:
         return true;
     };
 
+    let Some(count) = count_listeners(ctx, bot_voice_channel_id).await else {
+        return false;
+    };
+    
+    // if count > 0 {we check if channels match} else {the channel is empty so we can move}
+    if count > 0 {
+        return user_voice_channel_id == bot_voice_channel_id;
+    }
+
+    true
+}
+
+// get count of non bot users in voice
+pub async fn count_listeners(ctx: &Context<'_>, channel_id: ChannelId) -> Option<usize> {
     // channel id to guild voice channel
     let Ok(channel) = ctx
         .http()
-        .get_channel(bot_voice_channel_id).await 
+        .get_channel(channel_id).await 
     else {
-        return false;
+        return None;
     };
     let serenity::model::channel::Channel::Guild(guild_channel) = channel else {
-        return false;
+        return None;
     };
 
-    // get count of non bot users in voice
-    let Ok(count) = guild_channel
+    guild_channel
         .members(ctx.cache())
-        .map(|f| f.iter().filter(|p| !p.user.bot).count()) 
-    else {
-        return false;
-    };
-    
-    // if count > 0 {we check if channels match} else {the channel is empty so we can move}
-    if count > 0 {
-        return user_voice_channel_id == bot_voice_channel_id;
+        .map(|f| f.iter().filter(|p| !p.user.bot).count())
+        .ok()
+}
+
+// djs bypass vote skipping
+pub async fn is_dj(ctx: &Context<'_>, guild: &Guild) -> bool {
+    member_permission_level(ctx, guild).await >= PermissionLevel::Dj
+}
+
+// joins the author's voice channel, the global event handlers are added if the bot wasn't connected yet
+pub async fn join_author_channel(ctx: &Context<'_>, guild: &Guild) -> Result<Arc<Mutex<Call>>, CommandError> {
+    let user_voice = guild.voice_states.get(&ctx.author().id).ok_or(VoiceError::NotConnected)?;
+
+    let manager = songbird::get(ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
+    let handler = manager.get_or_insert(guild.id);
+
+    if !should_move_channels(ctx, guild, user_voice).await { return Err(VoiceError::DifferentVoiceChannel.into()) }
+
+    let connection = handler.lock().await.current_connection().and_then(|conn| conn.channel_id);
+
+    let handler = manager.join(guild.id, user_voice.channel_id.unwrap()).await?;
+    {
+        let mut handler_guard = handler.lock().await;
+
+        if connection.is_none() {
+            add_global_events(ctx, &mut handler_guard, handler.clone()).await;
+        }
+
+        let _ = handler_guard.deafen(true).await;
     }
+    Ok(handler)
+}
 
-    true
+// adds the global event handlers upon joining a channel
+pub async fn add_global_events(ctx: &Context<'_>, handler_guard: &mut Call, handler: Arc<Mutex<Call>>) {
+    GlobalEvents::from_context(ctx).register(handler_guard, handler).await;
+}
+
+// everything the global event handlers need, so they can be added without a command context
+pub struct GlobalEvents {
+    pub guild_id: u64,
+    pub channel_id: ChannelId,
+    pub http: Arc<Http>,
+    pub guild_states: GuildStates,
+    pub client: reqwest::Client,
+    pub queue_store: QueueStore
+}
+
+impl GlobalEvents {
+    pub fn from_context(ctx: &Context<'_>) -> Self {
+        Self {
+            guild_id: ctx.guild_id().map(|guild_id| guild_id.get()).unwrap_or(0),
+            channel_id: ctx.channel_id(),
+            http: ctx.serenity_context().http.clone(),
+            guild_states: ctx.data().guild_states.clone(),
+            client: ctx.data().reqwest_client.clone(),
+            queue_store: ctx.data().queue_store.clone()
+        }
+    }
+
+    pub async fn register(self, handler_guard: &mut Call, handler: Arc<Mutex<Call>>) {
+        let Self { guild_id, channel_id, http, guild_states, client, queue_store } = self;
+        guild_states.with(guild_id, |state| state.text_channel = Some(channel_id.get())).await;
+
+        handler_guard.add_global_event(Event::Track(TrackEvent::Play), LazyMetadataEventHandler {
+            handler: handler.clone(),
+            channel_id,
+            http,
+            guild_states: guild_states.clone(),
+            guild_id
+        });
+        handler_guard.add_global_event(Event::Track(TrackEvent::End), QueueLoopEventHandler {
+            handler: handler.clone(),
+            guild_states: guild_states.clone(),
+            guild_id,
+            client
+        });
+        for event in [TrackEvent::Play, TrackEvent::End] {
+            handler_guard.add_global_event(Event::Track(event), QueuePersistenceEventHandler {
+                handler: handler.clone(),
+                guild_states: guild_states.clone(),
+                guild_id,
+                queue_store: queue_store.clone()
+            });
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,37 @@
+use crate::data::Context;
+
+use crate::commands::{ error::{VoiceError, CommandError}, utils::{same_voice_channel, send_timed_reply} };
+
+const MAX_VOLUME: u32 = 200;
+
+// sets the playback volume in percent for the current and every following track
+#[poise::command(slash_command, prefix_command, guild_only, aliases("vol"))]
+pub async fn volume(ctx: Context<'_>, #[min = 0] #[max = 200] volume: Option<u32>) -> Result<(), CommandError> {
+    let guild = ctx.guild().unwrap().clone();
+    let guild_states = &ctx.data().guild_states;
+
+    let Some(volume) = volume else {
+        let volume = guild_states.with(guild.id.get(), |state| state.settings.volume).await;
+        let _ = send_timed_reply(&ctx, format!("Volume: {}%", (volume * 100.0).round()), None).await;
+        return Ok(());
+    };
+    if volume > MAX_VOLUME { return Err(CommandError::InvalidVolume); }
+
+    let manager = songbird::get(&ctx.serenity_context()).await.ok_or(VoiceError::NoManager)?;
+    let handler = manager.get(guild.id);
+    if let Some(handler) = &handler {
+        if !same_voice_channel(&guild, &ctx.author().id, handler.clone()).await { return Ok(()); }
+    }
+
+    let volume = volume as f32 / 100.0;
+    guild_states.update_settings(guild.id.get(), |settings| settings.volume = volume).await;
+
+    if let Some(handler) = handler {
+        let current_track = handler.lock().await.queue().current();
+        if let Some(current_track) = current_track {
+            current_track.set_volume(volume)?;
+        }
+    }
+    let _ = send_timed_reply(&ctx, format!("Volume set to {}%", (volume * 100.0).round()), None).await;
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use crate::data::Context;
+
+use crate::commands::{ error::CommandError, utils::send_timed_reply };
+
+// sets the percentage of listeners needed to skip someone else's track, 0 lets anyone skip
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn voteskip(ctx: Context<'_>, #[min = 0] #[max = 100] percent: Option<u32>) -> Result<(), CommandError> {
+    let guild_id = ctx.guild_id().unwrap().get();
+    let guild_states = &ctx.data().guild_states;
+
+    let Some(percent) = percent else {
+        let vote_skip_ratio = guild_states.with(guild_id, |state| state.settings.vote_skip_ratio).await;
+        let _ = send_timed_reply(&ctx, format!("Vote skip: {}% of listeners", (vote_skip_ratio * 100.0).round()), None).await;
+        return Ok(());
+    };
+    if percent > 100 { return Err(CommandError::InvalidPercentage); }
+
+    guild_states.update_settings(guild_id, |settings| settings.vote_skip_ratio = percent as f32 / 100.0).await;
+    let message = match percent {
+        0 => "Vote skip disabled".to_owned(),
+        percent => format!("Vote skip set to {}% of listeners", percent)
+    };
+    let _ = send_timed_reply(&ctx, message, None).await;
+    Ok(())
+}
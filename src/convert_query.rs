@@ -1,15 +1,26 @@
-use crate::{metadata::{AudioSource, TrackMetadata, UserMetadata, VideoMetadata}, api_integration::{spotify::{SpotifyClient, SpotifyError}, youtube::{YouTubeClient, YouTubeError}}};
+use crate::{metadata::{AudioSource, TrackMetadata, UserMetadata, VideoMetadata}, api_integration::{spotify::{SpotifyClient, SpotifyError, SpotifyTrackData}, youtube::{YouTubeClient, YouTubeError}}};
 use reqwest::Url;
 use songbird::input::Input;
+use std::time::Duration;
 use thiserror::Error as ThisError;
 
 #[derive(Debug)]
 pub enum MediaType {
-    YouTubeVideo { video_id: String },
-    YouTubePlaylist { playlist_id: String },
+    YouTubeVideo { video_id: String, start: Option<Duration> },
+    YouTubePlaylist { playlist_id: String, index: Option<usize> },
+    YouTubeVideoInPlaylist { video_id: String, playlist_id: String, index: Option<usize>, start: Option<Duration> },
     SpotifyTrack { track_id: String },
     SpotifyPlaylist { playlist_id: String },
     SpotifyAlbum { album_id: String },
+    SpotifyEpisode { episode_id: String },
+    SoundCloudTrack { url: String },
+    SoundCloudSet { url: String },
+    BandcampTrack { url: String },
+    BandcampAlbum { url: String },
+    DirectUrl { url: String },
+    RadioPlaylist { url: String },
+    OtherUrl { url: String, domain: String },
+    LocalSearch { query: String },
     Search { query: String }
 }
 
@@ -30,29 +41,32 @@ pub enum MediaTypeError {
     #[error("")]
     UrlSpotifyContentTypeInvalid { url: String },
     #[error("")]
-    EpisodesUnsupported,
+    UrlSoundCloudInvalid { url: String },
+    #[error("")]
+    UrlBandcampInvalid { url: String },
     #[error("")]
     DomainMissing
 }
 
 pub fn extract_media_type(query: &str) -> Result<MediaType, MediaTypeError> {
+    if let Some(query) = query.strip_prefix(crate::library::LOCAL_PREFIX) {
+        return Ok(MediaType::LocalSearch { query: query.trim().to_owned() });
+    }
     match Url::parse(query) {
         Ok(url) => {
             let domain = url.domain().ok_or(MediaTypeError::DomainMissing)?;
             match domain {
-                "www.youtube.com" | "youtube.com" => {
-                    if let Some(video_id) = url.query_pairs().into_owned().find(|p| p.0 == "v").map(|f| f.1){
-                        return Ok(MediaType::YouTubeVideo { video_id });
-                    } else if let Some(playlist_id) = url.query_pairs().into_owned().find(|p| p.0 == "list").map(|f| f.1) {
-                        return Ok(MediaType::YouTubePlaylist { playlist_id });
-                    }
-                    return Err(MediaTypeError::UrlYouTubeLongInvalid { url: url.to_string() }.into());
+                "www.youtube.com" | "youtube.com" | "m.youtube.com" | "www.m.youtube.com" | "music.youtube.com" | "www.music.youtube.com" => {
+                    let arguments = url.path_segments().map(|f| f.filter(|segment| !segment.is_empty()).collect::<Vec<&str>>()).unwrap_or_default();
+                    let video_id = match arguments.as_slice() {
+                        ["shorts" | "live" | "embed" | "v", video_id, ..] => Some(video_id.to_string()),
+                        _ => query_parameter(&url, "v")
+                    };
+                    return youtube_media_type(&url, video_id).ok_or(MediaTypeError::UrlYouTubeLongInvalid { url: url.to_string() });
                 },
                 "www.youtu.be" | "youtu.be" => {
-                    if let Some(video_id) = url.path().strip_prefix("/").map(|f| f.to_owned()) {
-                        return Ok(MediaType::YouTubeVideo { video_id });
-                    }
-                    return Err(MediaTypeError::UrlYouTubeShortInvalid { url: url.to_string() }.into());
+                    let video_id = url.path_segments().and_then(|mut segments| segments.next()).filter(|video_id| !video_id.is_empty()).map(|f| f.to_owned());
+                    return youtube_media_type(&url, video_id).ok_or(MediaTypeError::UrlYouTubeShortInvalid { url: url.to_string() });
                 },
                 "open.spotify.com" | "www.open.spotify.com" => {
                     let argumets = url.path_segments().map(|f| f.collect::<Vec<&str>>()).ok_or(MediaTypeError::UrlSpotifyArgumentsInvalid { url: url.to_string() })?;
@@ -63,11 +77,30 @@ pub fn extract_media_type(query: &str) -> Result<MediaType, MediaTypeError> {
                         "track" => MediaType::SpotifyTrack { track_id: id },
                         "playlist" => MediaType::SpotifyPlaylist { playlist_id: id },
                         "album" => MediaType::SpotifyAlbum { album_id: id },
-                        "episode" => return Err(MediaTypeError::EpisodesUnsupported.into()),
+                        "episode" => MediaType::SpotifyEpisode { episode_id: id },
                         _ => return Err(MediaTypeError::UrlSpotifyContentTypeInvalid { url: url.to_string() }.into())
                     });
                 }
-                _ => return Err(MediaTypeError::UnsupportedDomain { domain: domain.to_owned() }.into())
+                "soundcloud.com" | "www.soundcloud.com" | "m.soundcloud.com" | "on.soundcloud.com" => {
+                    let arguments = url.path_segments().map(|f| f.filter(|segment| !segment.is_empty()).collect::<Vec<&str>>()).unwrap_or_default();
+                    return match arguments.as_slice() {
+                        [_, "sets", _, ..] => Ok(MediaType::SoundCloudSet { url: url.to_string() }),
+                        [_, _, ..] => Ok(MediaType::SoundCloudTrack { url: url.to_string() }),
+                        [_] if domain == "on.soundcloud.com" => Ok(MediaType::SoundCloudTrack { url: url.to_string() }), // short links are resolved by the api
+                        _ => Err(MediaTypeError::UrlSoundCloudInvalid { url: url.to_string() })
+                    };
+                },
+                _ if domain.ends_with(".bandcamp.com") => {
+                    return match url.path_segments().and_then(|mut segments| segments.next()) {
+                        Some("track") => Ok(MediaType::BandcampTrack { url: url.to_string() }),
+                        Some("album") => Ok(MediaType::BandcampAlbum { url: url.to_string() }),
+                        _ => Err(MediaTypeError::UrlBandcampInvalid { url: url.to_string() })
+                    };
+                },
+                _ if crate::audio_probe::is_audio_url(&url) => return Ok(MediaType::DirectUrl { url: url.to_string() }),
+                _ if crate::radio::is_playlist_url(&url) => return Ok(MediaType::RadioPlaylist { url: url.to_string() }),
+                // could still be a radio stream or a podcast feed, that's only known after connecting to it
+                _ => return Ok(MediaType::OtherUrl { url: url.to_string(), domain: domain.to_owned() })
             }
         },
         Err(_) => {
@@ -76,21 +109,75 @@ pub fn extract_media_type(query: &str) -> Result<MediaType, MediaTypeError> {
     }
 }
 
+fn query_parameter(url: &Url, key: &str) -> Option<String> {
+    url.query_pairs().find(|p| p.0 == key).map(|f| f.1.into_owned()).filter(|value| !value.is_empty())
+}
+
+// links to a video inside of a playlist are left for the caller to decide on
+fn youtube_media_type(url: &Url, video_id: Option<String>) -> Option<MediaType> {
+    let start = extract_start_time(url);
+    // `index` counts from 1
+    let index = query_parameter(url, "index").and_then(|index| index.parse::<usize>().ok()).filter(|index| *index > 0);
+    // mixes are generated for every listener separately and can't be fetched
+    let playlist_id = query_parameter(url, "list").filter(|playlist_id| !playlist_id.starts_with("RD"));
+    match (video_id, playlist_id) {
+        (Some(video_id), Some(playlist_id)) => Some(MediaType::YouTubeVideoInPlaylist { video_id, playlist_id, index, start }),
+        (Some(video_id), None) => Some(MediaType::YouTubeVideo { video_id, start }),
+        (None, Some(playlist_id)) => Some(MediaType::YouTubePlaylist { playlist_id, index }),
+        (None, None) => None
+    }
+}
+
+// reads the `t` parameter of youtube urls
+fn extract_start_time(url: &Url) -> Option<Duration> {
+    let timestamp = url.query_pairs().find(|p| p.0 == "t").map(|f| f.1)?;
+    crate::utils::parse_timestamp(&timestamp).filter(|start| !start.is_zero())
+}
+
+// playlists also carry the number of entries which were left out
 pub enum ConvertedQuery {
     LiveVideo(MetaInput),
-    LivePlaylist(Vec<MetaInput>),
-    PendingPlaylist(Vec<PendingMetaInput>)
+    LivePlaylist(Vec<MetaInput>, usize),
+    PendingPlaylist(Vec<PendingMetaInput>, usize, Option<PendingPages>)
+}
+
+// pages of a playlist which are still loading after the first one got converted
+pub struct PendingPages {
+    pages: tokio::sync::mpsc::Receiver<crate::api_integration::spotify::SpotifyPlaylistData>,
+    added_by: UserMetadata,
+    client: reqwest::Client
+}
+
+impl PendingPages {
+    // the next page and how many of its entries were left out
+    pub async fn next(&mut self) -> Option<(Vec<PendingMetaInput>, usize)> {
+        let page = self.pages.recv().await?;
+        Some((spotify_pending_metainputs(page.tracks, &self.added_by, &self.client), page.skipped_episodes))
+    }
+}
+
+fn spotify_pending_metainputs(tracks: Vec<SpotifyTrackData>, added_by: &UserMetadata, client: &reqwest::Client) -> Vec<PendingMetaInput> {
+    let mut metainputs = vec![];
+    for track_data in tracks {
+        let query = track_data.query();
+        let input = YouTubeComposer::Query { query: query.clone(), spotify_track: Some(track_data.clone()), client: client.clone() }.into();
+        let metainput = PendingMetaInput { input, added_by: added_by.clone(), query, spotify_track: Some(track_data) };
+        metainputs.push(metainput);
+    }
+    metainputs
 }
 
 pub struct MetaInput {
     pub input: Input,
-    pub track_metadata: TrackMetadata
+    pub track_metadata: TrackMetadata,
+    pub start: Option<Duration>
 }
 
 pub struct PendingMetaInput {
     pub input: Input,
     pub query: String,
-    pub added_by: UserMetadata
+    pub added_by: UserMetadata,
+    pub spotify_track: Option<SpotifyTrackData>
 }
 
 #[derive(Debug, ThisError)]
@@ -105,12 +192,24 @@ pub enum ConversionError {
     YoutubeScrape(#[from] crate::scrapers::youtube::YoutubeScrapeError),
     #[error("")]
     RustyYtdl(#[from] rusty_ytdl::VideoError),
+    #[error("{0}")]
+    SoundCloud(#[from] crate::scrapers::soundcloud::SoundCloudError),
+    #[error("{0}")]
+    Bandcamp(#[from] crate::scrapers::bandcamp::BandcampError),
+    #[error("{0}")]
+    Podcast(#[from] crate::scrapers::podcast::PodcastError),
+    #[error("{0}")]
+    Radio(#[from] crate::radio::RadioError),
+    #[error("{0}")]
+    Library(#[from] crate::library::LibraryError),
+    #[error("{0}")]
+    Probe(#[from] crate::audio_probe::ProbeError),
     #[error("")]
     NoVideoFormat
 }
 
 pub enum YouTubeComposer {
-    Query { query: String, client: reqwest::Client },
+    Query { query: String, spotify_track: Option<SpotifyTrackData>, client: reqwest::Client },
     Metadata { metadata: VideoMetadata, client: reqwest::Client  }
 }
 
@@ -151,14 +250,13 @@ impl songbird::input::Compose for YouTubeComposer {
 
     async fn create_async(&mut self) -> Result<songbird::input::AudioStream<Box<dyn symphonia::core::io::MediaSource> > ,songbird::input::AudioStreamError> {
         match self {
-            Self::Query { query, client } => {
-                match crate::scrapers::youtube::search(&query).await {
+            Self::Query { query, spotify_track, client } => {
+                match crate::track_matching::resolve_query(query, spotify_track.as_ref()).await {
                     Ok(video_metadata) => {
                         let crate::metadata::AudioSource::YouTube { video_id } = video_metadata.audio_source else { panic!("youtube search returned non youtube source") };
                         match find_video_format(video_id).await {
                             Ok(url) => {
-                                let mut http_request = songbird::input::HttpRequest::new(client.clone(), url);
-                                http_request.create_async().await
+                                crate::http_stream::create_seekable_http_stream(client.clone(), url).await
                             },
                             Err(err) => {
                                 Err(songbird::input::AudioStreamError::Fail(err.into()))
@@ -173,8 +271,7 @@ impl songbird::input::Compose for YouTubeComposer {
                     AudioSource::YouTube { video_id } => {
                         match find_video_format(video_id).await {
                             Ok(url) => {
-                                let mut http_request = songbird::input::HttpRequest::new(client.clone(), url);
-                                http_request.create_async().await
+                                crate::http_stream::create_seekable_http_stream(client.clone(), url).await
                             },
                             Err(err) => {
                                 Err(songbird::input::AudioStreamError::Fail(err.into()))
@@ -184,6 +281,24 @@ impl songbird::input::Compose for YouTubeComposer {
                     AudioSource::File { path } => {
                         songbird::input::File::new(path).create_async().await
                     },
+                    AudioSource::Url { url } => {
+                        crate::http_stream::create_seekable_http_stream(client.clone(), url).await
+                    },
+                    AudioSource::SoundCloud { track_id, .. } => {
+                        match crate::scrapers::soundcloud::stream_url(client, track_id).await {
+                            Ok(url) => crate::http_stream::create_seekable_http_stream(client.clone(), url).await,
+                            Err(err) => Err(songbird::input::AudioStreamError::Fail(err.into()))
+                        }
+                    },
+                    AudioSource::Bandcamp { url } => {
+                        match crate::scrapers::bandcamp::stream_url(client, &url).await {
+                            Ok(url) => crate::http_stream::create_seekable_http_stream(client.clone(), url).await,
+                            Err(err) => Err(songbird::input::AudioStreamError::Fail(err.into()))
+                        }
+                    },
+                    AudioSource::Radio { url, stream_title } => {
+                        crate::radio::create_radio_stream(client.clone(), url, stream_title).await
+                    },
                     AudioSource::Jeja { filename } => {
                         crate::scrapers::jeja::tts_download(&filename, client.clone()).await
                             .map_err(|err| songbird::input::AudioStreamError::Fail(err.into()))?;
@@ -195,7 +310,22 @@ impl songbird::input::Compose for YouTubeComposer {
     }
 
     async fn aux_metadata(&mut self) -> Result<songbird::input::AuxMetadata,songbird::input::AudioStreamError> {
-        Err(songbird::input::AudioStreamError::Unsupported)
+        match self {
+            Self::Query { .. } => Err(songbird::input::AudioStreamError::Unsupported),
+            Self::Metadata { metadata, .. } => {
+                let source_url = match &metadata.audio_source {
+                    AudioSource::YouTube { video_id } => Some(format!("https://youtu.be/{}", video_id)),
+                    AudioSource::Url { url } | AudioSource::Radio { url, .. } | AudioSource::SoundCloud { url, .. } | AudioSource::Bandcamp { url } => Some(url.clone()),
+                    _ => None
+                };
+                Ok(songbird::input::AuxMetadata {
+                    title: Some(metadata.title.clone()),
+                    duration: Some(metadata.duration),
+                    source_url,
+                    ..Default::default()
+                })
+            }
+        }
     }
 
     fn should_create_async(&self) -> bool {
@@ -209,59 +339,192 @@ impl Into<Input> for YouTubeComposer {
     }
 }
 
-pub async fn convert_query(youtube_client: &YouTubeClient, spotify_client: &SpotifyClient, query: &str, added_by: UserMetadata, client: reqwest::Client) -> Result<ConvertedQuery, ConversionError> {
-    return Ok(match extract_media_type(query)? {
-        MediaType::YouTubeVideo { video_id } => {
+pub async fn convert_query(youtube_client: &YouTubeClient, spotify_client: &SpotifyClient, library: &crate::library::Library, query: &str, added_by: UserMetadata, client: reqwest::Client) -> Result<ConvertedQuery, ConversionError> {
+    convert_media_type(youtube_client, spotify_client, library, extract_media_type(query)?, added_by, client).await
+}
+
+// a video inside of a playlist is queued on its own unless it was already resolved to the playlist
+pub async fn convert_media_type(youtube_client: &YouTubeClient, spotify_client: &SpotifyClient, library: &crate::library::Library, media_type: MediaType, added_by: UserMetadata, client: reqwest::Client) -> Result<ConvertedQuery, ConversionError> {
+    return Ok(match media_type {
+        MediaType::YouTubeVideo { video_id, start } | MediaType::YouTubeVideoInPlaylist { video_id, start, .. } => {
             let video_metadata = youtube_client.video(&video_id).await?;
             let input = YouTubeComposer::Metadata { metadata: video_metadata.clone(), client }.into();
             let track_metadata = TrackMetadata { video_metadata, added_by };
-            ConvertedQuery::LiveVideo(MetaInput { input, track_metadata })
+            ConvertedQuery::LiveVideo(MetaInput { input, track_metadata, start })
         },
-        MediaType::YouTubePlaylist { playlist_id } => {
-            let playlist_video_metadata = youtube_client.playlist(&playlist_id).await?;
+        MediaType::YouTubePlaylist { playlist_id, index } => {
+            let playlist_data = youtube_client.playlist(&playlist_id, index.unwrap_or(1) - 1).await?;
             let mut metainputs = vec![];
-            for video_metadata in playlist_video_metadata {
+            for video_metadata in playlist_data.videos {
                 let input = YouTubeComposer::Metadata { metadata: video_metadata.clone(), client: client.clone() }.into();
                 let track_metadata = TrackMetadata { video_metadata, added_by: added_by.clone() };
-                let metainput = MetaInput { input, track_metadata };
+                let metainput = MetaInput { input, track_metadata, start: None };
                 metainputs.push(metainput);
             }
-            ConvertedQuery::LivePlaylist(metainputs)
+            ConvertedQuery::LivePlaylist(metainputs, playlist_data.skipped)
         },
         MediaType::SpotifyTrack { track_id } => {
-            let track_data = spotify_client.track(&track_id)?;
-            let video_metadata = crate::scrapers::youtube::search(&format!("{} by {}", track_data.title, track_data.artists.join(", "))).await?;
+            let track_data = spotify_client.track(&track_id).await?;
+            let video_metadata = crate::track_matching::find_spotify_track(&track_data).await?;
             let input = YouTubeComposer::Metadata { metadata: video_metadata.clone(), client }.into();
             let track_metadata = TrackMetadata { video_metadata, added_by };
-            ConvertedQuery::LiveVideo(MetaInput { input, track_metadata })
+            ConvertedQuery::LiveVideo(MetaInput { input, track_metadata, start: None })
         },
         MediaType::SpotifyPlaylist { playlist_id } => {
-            let playlist_data = spotify_client.playlist(&playlist_id)?;
+            let pages = spotify_client.playlist(&playlist_id).await?;
+            let metainputs = spotify_pending_metainputs(pages.first.tracks, &added_by, &client);
+            ConvertedQuery::PendingPlaylist(metainputs, pages.first.skipped_episodes, Some(PendingPages { pages: pages.rest, added_by, client }))
+        },
+        MediaType::SpotifyAlbum { album_id } => {
+            let pages = spotify_client.album(&album_id).await?;
+            let metainputs = spotify_pending_metainputs(pages.first.tracks, &added_by, &client);
+            ConvertedQuery::PendingPlaylist(metainputs, pages.first.skipped_episodes, Some(PendingPages { pages: pages.rest, added_by, client }))
+        },
+        MediaType::SpotifyEpisode { episode_id } => {
+            let episode_data = spotify_client.episode(&episode_id).await?;
+            let video_metadata = crate::scrapers::podcast::find_episode(&client, &episode_data.show, &episode_data.title).await?;
+            let input = YouTubeComposer::Metadata { metadata: video_metadata.clone(), client }.into();
+            let track_metadata = TrackMetadata { video_metadata, added_by };
+            ConvertedQuery::LiveVideo(MetaInput { input, track_metadata, start: None })
+        },
+        MediaType::SoundCloudTrack { url } => {
+            let video_metadata = crate::scrapers::soundcloud::resolve_track(&client, &url).await?;
+            let input = YouTubeComposer::Metadata { metadata: video_metadata.clone(), client }.into();
+            let track_metadata = TrackMetadata { video_metadata, added_by };
+            ConvertedQuery::LiveVideo(MetaInput { input, track_metadata, start: None })
+        },
+        MediaType::SoundCloudSet { url } => {
+            let set_video_metadata = crate::scrapers::soundcloud::resolve_set(&client, &url).await?;
             let mut metainputs = vec![];
-            for track_data in playlist_data {
-                let query = format!("{} by {}", track_data.title, track_data.artists.join(", "));
-                let input = YouTubeComposer::Query { query: query.clone(), client: client.clone() }.into();
-                let metainput = PendingMetaInput { input, added_by: added_by.clone(), query };
-                metainputs.push(metainput);
+            for video_metadata in set_video_metadata {
+                let input = YouTubeComposer::Metadata { metadata: video_metadata.clone(), client: client.clone() }.into();
+                let track_metadata = TrackMetadata { video_metadata, added_by: added_by.clone() };
+                metainputs.push(MetaInput { input, track_metadata, start: None });
             }
-            ConvertedQuery::PendingPlaylist(metainputs)
+            ConvertedQuery::LivePlaylist(metainputs, 0)
         },
-        MediaType::SpotifyAlbum { album_id } => {
-            let album_data = spotify_client.album(&album_id)?;
+        MediaType::BandcampTrack { url } => {
+            let video_metadata = crate::scrapers::bandcamp::track(&client, &url).await?;
+            let input = YouTubeComposer::Metadata { metadata: video_metadata.clone(), client }.into();
+            let track_metadata = TrackMetadata { video_metadata, added_by };
+            ConvertedQuery::LiveVideo(MetaInput { input, track_metadata, start: None })
+        },
+        MediaType::BandcampAlbum { url } => {
+            let album_video_metadata = crate::scrapers::bandcamp::album(&client, &url).await?;
             let mut metainputs = vec![];
-            for track_data in album_data {
-                let query = format!("{} by {}", track_data.title, track_data.artists.join(", "));
-                let input = YouTubeComposer::Query { query: query.clone(), client: client.clone() }.into();
-                let metainput = PendingMetaInput { input, added_by: added_by.clone(), query };
-                metainputs.push(metainput);
+            for video_metadata in album_video_metadata {
+                let input = YouTubeComposer::Metadata { metadata: video_metadata.clone(), client: client.clone() }.into();
+                let track_metadata = TrackMetadata { video_metadata, added_by: added_by.clone() };
+                metainputs.push(MetaInput { input, track_metadata, start: None });
             }
-            ConvertedQuery::PendingPlaylist(metainputs)
+            ConvertedQuery::LivePlaylist(metainputs, 0)
+        },
+        MediaType::DirectUrl { url } => {
+            // icecast mounts often end with .mp3 or .ogg
+            let video_metadata = match crate::radio::probe_station(&client, &url).await? {
+                Some(video_metadata) => video_metadata,
+                None => crate::audio_probe::probe_url(client.clone(), url).await?
+            };
+            let input = YouTubeComposer::Metadata { metadata: video_metadata.clone(), client }.into();
+            let track_metadata = TrackMetadata { video_metadata, added_by };
+            ConvertedQuery::LiveVideo(MetaInput { input, track_metadata, start: None })
+        },
+        MediaType::RadioPlaylist { url } => {
+            let stream_url = crate::radio::resolve_playlist(&client, &url).await?;
+            let video_metadata = crate::radio::probe_station(&client, &stream_url).await?.ok_or(crate::radio::RadioError::NotAStream)?;
+            let input = YouTubeComposer::Metadata { metadata: video_metadata.clone(), client }.into();
+            let track_metadata = TrackMetadata { video_metadata, added_by };
+            ConvertedQuery::LiveVideo(MetaInput { input, track_metadata, start: None })
+        },
+        MediaType::OtherUrl { url, domain } => {
+            // podcast feeds start with their latest episode
+            let video_metadata = match crate::radio::probe_station(&client, &url).await? {
+                Some(video_metadata) => video_metadata,
+                None => crate::scrapers::podcast::feed(&client, &url).await?
+                    .and_then(|episodes| episodes.into_iter().next())
+                    .map(|episode| episode.video_metadata)
+                    .ok_or(MediaTypeError::UnsupportedDomain { domain })?
+            };
+            let input = YouTubeComposer::Metadata { metadata: video_metadata.clone(), client }.into();
+            let track_metadata = TrackMetadata { video_metadata, added_by };
+            ConvertedQuery::LiveVideo(MetaInput { input, track_metadata, start: None })
+        },
+        MediaType::LocalSearch { query } => {
+            let library_track = library.search(&query).await?.into_iter().next().ok_or(crate::library::LibraryError::NoMatch)?;
+            let video_metadata = library_track.video_metadata();
+            let input = YouTubeComposer::Metadata { metadata: video_metadata.clone(), client }.into();
+            let track_metadata = TrackMetadata { video_metadata, added_by };
+            ConvertedQuery::LiveVideo(MetaInput { input, track_metadata, start: None })
         },
         MediaType::Search { query } => {
-            let video_metadata = crate::scrapers::youtube::search(&query).await?;
+            let video_metadata = match crate::scrapers::youtube::search(&query).await {
+                Ok(video_metadata) => video_metadata,
+                // falls back to the library when youtube has nothing, it's only searched if it's enabled
+                Err(err) => match library.search(&query).await.ok().and_then(|tracks| tracks.into_iter().next()) {
+                    Some(library_track) => library_track.video_metadata(),
+                    None => return Err(err.into())
+                }
+            };
             let input = YouTubeComposer::Metadata { metadata: video_metadata.clone(), client }.into();
             let track_metadata = TrackMetadata { video_metadata, added_by };
-            ConvertedQuery::LiveVideo(MetaInput { input, track_metadata })
+            ConvertedQuery::LiveVideo(MetaInput { input, track_metadata, start: None })
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video_id(query: &str) -> Option<String> {
+        match extract_media_type(query) {
+            Ok(MediaType::YouTubeVideo { video_id, .. }) => Some(video_id),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn extracts_videos_from_every_youtube_domain() {
+        for query in [
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://music.youtube.com/watch?v=dQw4w9WgXcQ&feature=share",
+            "https://m.youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://youtube.com/shorts/dQw4w9WgXcQ?si=abc",
+            "https://www.youtube.com/live/dQw4w9WgXcQ",
+            "https://www.youtube.com/embed/dQw4w9WgXcQ",
+            "https://youtu.be/dQw4w9WgXcQ"
+        ] {
+            assert_eq!(video_id(query).as_deref(), Some("dQw4w9WgXcQ"), "{}", query);
+        }
+    }
+
+    #[test]
+    fn reads_start_time() {
+        assert!(matches!(extract_media_type("https://youtu.be/dQw4w9WgXcQ?t=83"), Ok(MediaType::YouTubeVideo { start: Some(start), .. }) if start.as_secs() == 83));
+        assert!(matches!(extract_media_type("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=1m23s"), Ok(MediaType::YouTubeVideo { start: Some(start), .. }) if start.as_secs() == 83));
+        assert!(matches!(extract_media_type("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=0"), Ok(MediaType::YouTubeVideo { start: None, .. })));
+    }
+
+    #[test]
+    fn extracts_playlists() {
+        assert!(matches!(extract_media_type("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PLabc&index=3"),
+            Ok(MediaType::YouTubeVideoInPlaylist { video_id, playlist_id, index: Some(3), .. }) if video_id == "dQw4w9WgXcQ" && playlist_id == "PLabc"));
+        assert!(matches!(extract_media_type("https://www.youtube.com/playlist?list=PLabc"),
+            Ok(MediaType::YouTubePlaylist { playlist_id, index: None }) if playlist_id == "PLabc"));
+        // mixes can't be fetched, so only the video is left
+        assert!(matches!(extract_media_type("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=RDdQw4w9WgXcQ&index=0"), Ok(MediaType::YouTubeVideo { .. })));
+    }
+
+    #[test]
+    fn rejects_youtube_links_without_a_video() {
+        assert!(matches!(extract_media_type("https://www.youtube.com/feed/trending"), Err(MediaTypeError::UrlYouTubeLongInvalid { .. })));
+        assert!(matches!(extract_media_type("https://youtu.be/"), Err(MediaTypeError::UrlYouTubeShortInvalid { .. })));
+    }
+
+    #[test]
+    fn everything_else() {
+        assert!(matches!(extract_media_type("https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC"), Ok(MediaType::SpotifyTrack { track_id }) if track_id == "4uLU6hMCjMI75M1A2tKUQC"));
+        assert!(matches!(extract_media_type("local: demo tape"), Ok(MediaType::LocalSearch { query }) if query == "demo tape"));
+        assert!(matches!(extract_media_type("never gonna give you up"), Ok(MediaType::Search { query }) if query == "never gonna give you up"));
+    }
+}
@@ -2,16 +2,25 @@ use poise::ReplyHandle;
 use serenity::model::channel::Message;
 use crate::convert_query::ConvertedQuery;
 use tokio::{sync::Mutex, task::AbortHandle};
+use std::sync::Arc;
 use crate::metadata::UserMetadata;
+use crate::guild_state::GuildStates;
+use crate::queue_persistence::QueueStore;
+use crate::playlists::PlaylistStore;
+use crate::library::Library;
 use std::collections::HashMap;
 
 pub type Context<'a> = poise::Context<'a, Data, crate::commands::error::CommandError>;
 pub struct Data {
-    pub cleanups: Mutex<Vec<Cleanup>>,
+    pub cleanups: Arc<Mutex<Vec<Cleanup>>>,
     pub spotify_client: crate::api_integration::spotify::SpotifyClient,
     pub youtube_client: crate::api_integration::youtube::YouTubeClient,
     pub afk_timeout_abort_handle_map: Mutex<HashMap<u64, AbortHandle>>,
-    pub reqwest_client: reqwest::Client
+    pub reqwest_client: reqwest::Client,
+    pub guild_states: GuildStates,
+    pub queue_store: QueueStore,
+    pub playlist_store: PlaylistStore,
+    pub library: Library
 }
 
 #[derive(Clone)]
@@ -21,12 +30,22 @@ pub struct Cleanup {
 }
 
 impl Data {
-    pub fn new(spotify_client: crate::api_integration::spotify::SpotifyClient, youtube_client: crate::api_integration::youtube::YouTubeClient) -> Self {
-        Self { cleanups: Mutex::new(vec![]), spotify_client, youtube_client, afk_timeout_abort_handle_map: Mutex::new(HashMap::new()), reqwest_client: reqwest::Client::new() }
+    pub fn new(spotify_client: crate::api_integration::spotify::SpotifyClient, youtube_client: crate::api_integration::youtube::YouTubeClient, guild_states: GuildStates, queue_store: QueueStore, playlist_store: PlaylistStore, library: Library) -> Self {
+        Self { cleanups: Arc::new(Mutex::new(vec![])), spotify_client, youtube_client, afk_timeout_abort_handle_map: Mutex::new(HashMap::new()), reqwest_client: crate::url_guard::guarded_client(), guild_states, queue_store, playlist_store, library }
     }
 
     pub async fn convert_query(&self, query: &str, added_by: UserMetadata) -> Result<ConvertedQuery, crate::convert_query::ConversionError> {
-        let result = crate::convert_query::convert_query(&self.youtube_client, &self.spotify_client, query, added_by, self.reqwest_client.clone()).await;
+        let result = crate::convert_query::convert_query(&self.youtube_client, &self.spotify_client, &self.library, query, added_by, self.reqwest_client.clone()).await;
+
+        if let Err(err) = &result {
+            log::error!("{}", err.to_string());
+        }
+
+        result
+    }
+
+    pub async fn convert_media_type(&self, media_type: crate::convert_query::MediaType, added_by: UserMetadata) -> Result<ConvertedQuery, crate::convert_query::ConversionError> {
+        let result = crate::convert_query::convert_media_type(&self.youtube_client, &self.spotify_client, &self.library, media_type, added_by, self.reqwest_client.clone()).await;
 
         if let Err(err) = &result {
             log::error!("{}", err.to_string());
@@ -0,0 +1,95 @@
+use std::{collections::{HashMap, VecDeque}, path::PathBuf, sync::Arc};
+
+use crate::{metadata::TrackMetadata, settings::{load_settings, save_settings, GuildSettings}, storage::StorageError};
+use serde::{Deserialize, Serialize};
+use songbird::tracks::LoopState;
+use tokio::sync::Mutex;
+
+const HISTORY_LENGTH: usize = 100;
+
+#[derive(Debug, Default)]
+pub struct GuildState {
+    pub loop_mode: LoopMode,
+    pub history: VecDeque<TrackMetadata>,
+    pub text_channel: Option<u64>, // channel the bot was summoned from
+    pub settings: GuildSettings
+}
+
+impl GuildState {
+    // the oldest entries are dropped once the history is full
+    pub fn push_history(&mut self, track_metadata: TrackMetadata) {
+        if self.history.len() == HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(track_metadata);
+    }
+}
+
+// per guild state shared between commands and songbird event handlers
+#[derive(Debug, Clone)]
+pub struct GuildStates {
+    states: Arc<Mutex<HashMap<u64, GuildState>>>,
+    settings_path: Arc<PathBuf>,
+    write_lock: Arc<Mutex<()>>
+}
+
+impl GuildStates {
+    pub async fn load(settings_path: PathBuf) -> Result<Self, StorageError> {
+        let states = load_settings(&settings_path).await?
+            .into_iter()
+            .map(|(guild_id, settings)| (guild_id, GuildState { settings, ..Default::default() }))
+            .collect();
+        Ok(Self { states: Arc::new(Mutex::new(states)), settings_path: Arc::new(settings_path), write_lock: Arc::new(Mutex::new(())) })
+    }
+
+    // changes made to the settings here aren't saved, use update_settings for that
+    pub async fn with<T>(&self, guild_id: u64, func: impl FnOnce(&mut GuildState) -> T) -> T {
+        func(self.states.lock().await.entry(guild_id).or_default())
+    }
+
+    // the file is written without holding the states, so other guilds aren't blocked by it
+    pub async fn update_settings<T>(&self, guild_id: u64, func: impl FnOnce(&mut GuildSettings) -> T) -> T {
+        // taken first so the snapshot written last is always the newest one
+        let _write_guard = self.write_lock.lock().await;
+        let (result, settings) = {
+            let mut states = self.states.lock().await;
+            let result = func(&mut states.entry(guild_id).or_default().settings);
+            let settings = states.iter().map(|(guild_id, state)| (*guild_id, state.settings.clone())).collect::<HashMap<u64, GuildSettings>>();
+            (result, settings)
+        };
+
+        let settings = settings.iter().map(|(guild_id, settings)| (*guild_id, settings)).collect();
+        if let Err(err) = save_settings(&self.settings_path, &settings).await {
+            log::error!("{}", err);
+        }
+        result
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, poise::ChoiceParameter)]
+pub enum LoopMode {
+    #[default]
+    Off,
+    Track,
+    Queue
+}
+
+impl LoopMode {
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Off => Self::Track,
+            Self::Track => Self::Queue,
+            Self::Queue => Self::Off
+        }
+    }
+
+    // the remaining track loop count is stored on the track itself
+    pub fn describe(&self, loops: Option<LoopState>) -> String {
+        match (self, loops) {
+            (Self::Track, Some(LoopState::Finite(count))) => format!("loop: track ({} left)", count),
+            (Self::Track, _) => "loop: track".to_owned(),
+            (Self::Queue, _) => "loop: queue".to_owned(),
+            (Self::Off, _) => "loop: off".to_owned()
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::{io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult, SeekFrom}, pin::Pin, task::{Context, Poll}, future::Future};
+
+use futures::{Stream, StreamExt, TryStreamExt};
+use reqwest::{Client, header::{ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_TYPE, RANGE}};
+use songbird::input::{AsyncAdapterStream, AsyncMediaSource, AudioStream, AudioStreamError};
+use symphonia::core::{io::MediaSource, probe::Hint};
+use tokio::{io::{AsyncRead, AsyncSeek, ReadBuf}, task::JoinHandle};
+
+type ByteStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>, reqwest::Error>> + Send + Sync>>;
+
+// http body which supports seeking by reissuing range requests, songbird's HttpRequest only supports resuming
+pub struct SeekableHttpStream {
+    client: Client,
+    url: String,
+    len: Option<u64>,
+    seekable: bool,
+    position: u64,
+    stream: ByteStream,
+    chunk: Vec<u8>,
+    chunk_offset: usize,
+    pending_seek: Option<(u64, JoinHandle<IoResult<ByteStream>>)>
+}
+
+impl SeekableHttpStream {
+    pub async fn connect(client: Client, url: String) -> Result<(Self, Option<Hint>), AudioStreamError> {
+        let response = request(&client, &url, 0, None).await.map_err(|err| AudioStreamError::Fail(err.into()))?;
+        let headers = response.headers();
+
+        let hint = headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| {
+                let mut hint = Hint::new();
+                hint.mime_type(value);
+                hint
+            });
+        let len = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        let seekable = headers
+            .get(ACCEPT_RANGES)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value == "bytes");
+
+        let stream = SeekableHttpStream {
+            client,
+            url,
+            len,
+            seekable,
+            position: 0,
+            stream: into_byte_stream(response),
+            chunk: vec![],
+            chunk_offset: 0,
+            pending_seek: None
+        };
+        Ok((stream, hint))
+    }
+}
+
+// creates an audio stream which songbird can seek through
+pub async fn create_seekable_http_stream(client: Client, url: String) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+    let (stream, hint) = SeekableHttpStream::connect(client, url).await?;
+    let input = AsyncAdapterStream::new(Box::new(stream), 64 * 1024);
+    Ok(AudioStream { input: Box::new(input) as Box<dyn MediaSource>, hint })
+}
+
+async fn request(client: &Client, url: &str, offset: u64, len: Option<u64>) -> IoResult<reqwest::Response> {
+    let mut request = client.get(url);
+    // some hosts (youtube) expect an explicit upper bound in range requests
+    match len {
+        Some(len) => request = request.header(RANGE, format!("bytes={}-{}", offset, len.saturating_sub(1))),
+        None if offset > 0 => request = request.header(RANGE, format!("bytes={}-", offset)),
+        None => ()
+    }
+
+    let response = request.send().await.map_err(|err| IoError::new(IoErrorKind::Other, err))?;
+    if !response.status().is_success() {
+        return Err(IoError::new(IoErrorKind::Other, format!("failed with http status code: {}", response.status())));
+    }
+    Ok(response)
+}
+
+fn into_byte_stream(response: reqwest::Response) -> ByteStream {
+    Box::pin(response.bytes_stream().map_ok(|bytes| bytes.to_vec()))
+}
+
+impl AsyncRead for SeekableHttpStream {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<IoResult<()>> {
+        while self.chunk_offset >= self.chunk.len() {
+            match self.stream.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    self.chunk = chunk;
+                    self.chunk_offset = 0;
+                },
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Err(IoError::new(IoErrorKind::Other, err))),
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+
+        let amount = buf.remaining().min(self.chunk.len() - self.chunk_offset);
+        let start = self.chunk_offset;
+        buf.put_slice(&self.chunk[start..start + amount]);
+        self.chunk_offset += amount;
+        self.position += amount as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for SeekableHttpStream {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> IoResult<()> {
+        if !self.seekable { return Err(IoErrorKind::Unsupported.into()); }
+
+        let target = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.len.ok_or(IoError::from(IoErrorKind::Unsupported))?.checked_add_signed(delta)
+        }.ok_or(IoError::from(IoErrorKind::InvalidInput))?;
+
+        let (client, url, len) = (self.client.clone(), self.url.clone(), self.len);
+        let handle = tokio::task::spawn(async move {
+            request(&client, &url, target, len).await.map(into_byte_stream)
+        });
+        self.pending_seek = Some((target, handle));
+        Ok(())
+    }
+
+    fn poll_complete(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<u64>> {
+        let Some((target, handle)) = &mut self.pending_seek else { return Poll::Ready(Ok(self.position)); };
+        let target = *target;
+
+        let result = match Pin::new(handle).poll(cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return Poll::Pending
+        };
+        self.pending_seek = None;
+
+        let stream = result.map_err(|err| IoError::new(IoErrorKind::Other, err))??;
+        self.stream = stream;
+        self.chunk.clear();
+        self.chunk_offset = 0;
+        self.position = target;
+        Poll::Ready(Ok(target))
+    }
+}
+
+#[serenity::async_trait]
+impl AsyncMediaSource for SeekableHttpStream {
+    fn is_seekable(&self) -> bool {
+        self.seekable
+    }
+
+    async fn byte_len(&self) -> Option<u64> {
+        self.len
+    }
+
+    async fn try_resume(&mut self, _offset: u64) -> Result<Box<dyn AsyncMediaSource>, AudioStreamError> {
+        if !self.seekable { return Err(AudioStreamError::Unsupported); }
+
+        // the adapter doesn't account for seeks in the offset it passes, so the tracked position is used instead
+        let response = request(&self.client, &self.url, self.position, self.len).await.map_err(|err| AudioStreamError::Fail(err.into()))?;
+        let stream = SeekableHttpStream {
+            client: self.client.clone(),
+            url: self.url.clone(),
+            len: self.len,
+            seekable: self.seekable,
+            position: self.position,
+            stream: into_byte_stream(response),
+            chunk: vec![],
+            chunk_offset: 0,
+            pending_seek: None
+        };
+        Ok(Box::new(stream))
+    }
+}
@@ -0,0 +1,157 @@
+use std::{path::{Path, PathBuf}, sync::{atomic::{AtomicBool, Ordering}, Arc}, time::Duration};
+
+use crate::{audio_probe::{is_audio_file, read_tags}, metadata::{AudioSource, VideoMetadata}};
+use symphonia::core::probe::Hint;
+use thiserror::Error as ThisError;
+use tokio::sync::RwLock;
+
+pub const LOCAL_PREFIX: &str = "local:";
+
+#[derive(Debug, ThisError)]
+pub enum LibraryError {
+    #[error("No music library is configured")]
+    Disabled,
+    #[error("The library is already being scanned")]
+    Scanning,
+    #[error("No matching tracks in the library")]
+    NoMatch,
+    #[error("Couldn't read the library directory")]
+    Io(#[from] std::io::Error)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum LibraryGroup {
+    Album,
+    Artist
+}
+
+#[derive(Debug, Clone)]
+pub struct LibraryTrack {
+    pub path: PathBuf,
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub track_number: Option<u32>,
+    pub duration: Duration
+}
+
+impl LibraryTrack {
+    pub fn video_metadata(&self) -> VideoMetadata {
+        let title = match &self.artist {
+            Some(artist) => format!("{} - {}", artist, self.title),
+            None => self.title.clone()
+        };
+        VideoMetadata { title, duration: self.duration, audio_source: AudioSource::File { path: self.path.clone() } }
+    }
+
+    // every term has to appear in the tags or the file name
+    fn matches(&self, terms: &[String]) -> bool {
+        let haystack = format!(
+            "{} {} {} {}",
+            self.title,
+            self.artist.as_deref().unwrap_or_default(),
+            self.album.as_deref().unwrap_or_default(),
+            self.path.file_name().map(|file_name| file_name.to_string_lossy()).unwrap_or_default()
+        ).to_lowercase();
+        terms.iter().all(|term| haystack.contains(term))
+    }
+}
+
+// index of a music directory shared by all guilds, it's only rebuilt on startup and on rescans
+#[derive(Debug, Clone)]
+pub struct Library {
+    root: Option<Arc<PathBuf>>,
+    tracks: Arc<RwLock<Vec<LibraryTrack>>>,
+    scanning: Arc<AtomicBool>
+}
+
+impl Library {
+    pub fn new(root: Option<PathBuf>) -> Self {
+        Self { root: root.map(Arc::new), tracks: Arc::new(RwLock::new(vec![])), scanning: Arc::new(AtomicBool::new(false)) }
+    }
+
+    // returns the number of indexed tracks, the old index is kept until the scan finishes
+    pub async fn scan(&self) -> Result<usize, LibraryError> {
+        let root = self.root.clone().ok_or(LibraryError::Disabled)?;
+        if self.scanning.swap(true, Ordering::SeqCst) { return Err(LibraryError::Scanning); }
+
+        let result = tokio::task::spawn_blocking(move || index(&root)).await;
+        self.scanning.store(false, Ordering::SeqCst);
+
+        let tracks = result.map_err(std::io::Error::other)??;
+        let tracks_len = tracks.len();
+        *self.tracks.write().await = tracks;
+        Ok(tracks_len)
+    }
+
+    pub async fn search(&self, query: &str) -> Result<Vec<LibraryTrack>, LibraryError> {
+        if self.root.is_none() { return Err(LibraryError::Disabled); }
+        let terms = query.to_lowercase().split_whitespace().map(|term| term.to_owned()).collect::<Vec<String>>();
+        if terms.is_empty() { return Err(LibraryError::NoMatch); }
+
+        let tracks = self.tracks.read().await.iter().filter(|track| track.matches(&terms)).cloned().collect::<Vec<LibraryTrack>>();
+        if tracks.is_empty() { return Err(LibraryError::NoMatch); }
+        Ok(tracks)
+    }
+
+    // exact names are preferred, otherwise every album or artist containing the name is used
+    pub async fn group(&self, group: LibraryGroup, name: &str) -> Result<Vec<LibraryTrack>, LibraryError> {
+        if self.root.is_none() { return Err(LibraryError::Disabled); }
+        let name = name.trim().to_lowercase();
+        if name.is_empty() { return Err(LibraryError::NoMatch); }
+
+        let tracks = self.tracks.read().await;
+        let group_name = |track: &LibraryTrack| match group {
+            LibraryGroup::Album => track.album.as_ref().map(|album| album.to_lowercase()),
+            LibraryGroup::Artist => track.artist.as_ref().map(|artist| artist.to_lowercase())
+        };
+
+        let mut matches = tracks.iter().filter(|track| group_name(track).is_some_and(|group_name| group_name == name)).cloned().collect::<Vec<LibraryTrack>>();
+        if matches.is_empty() {
+            matches = tracks.iter().filter(|track| group_name(track).is_some_and(|group_name| group_name.contains(&name))).cloned().collect();
+        }
+        if matches.is_empty() { return Err(LibraryError::NoMatch); }
+        Ok(matches)
+    }
+}
+
+// symlinks aren't followed so links pointing back up the tree can't loop forever
+fn index(root: &Path) -> Result<Vec<LibraryTrack>, std::io::Error> {
+    let mut directories = vec![root.to_path_buf()];
+    std::fs::read_dir(root)?; // only the root has to be readable, unreadable subdirectories are skipped
+
+    let mut tracks = vec![];
+    while let Some(directory) = directories.pop() {
+        let Ok(entries) = std::fs::read_dir(&directory) else { continue; };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else { continue; };
+            if file_type.is_dir() {
+                directories.push(path);
+            } else if file_type.is_file() && is_audio_file(&path) {
+                match read_track(path.clone()) {
+                    Some(track) => tracks.push(track),
+                    None => log::warn!("couldn't read the tags of {}", path.display())
+                }
+            }
+        }
+    }
+
+    // albums stay in track order when queued as a group
+    tracks.sort_by(|a, b| {
+        (a.artist.as_deref(), a.album.as_deref(), a.track_number, &a.path).cmp(&(b.artist.as_deref(), b.album.as_deref(), b.track_number, &b.path))
+    });
+    Ok(tracks)
+}
+
+fn read_track(path: PathBuf) -> Option<LibraryTrack> {
+    let file = std::fs::File::open(&path).ok()?;
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let tags = read_tags(Box::new(file), hint).ok()?;
+    let title = tags.title.unwrap_or_else(|| path.file_stem().map(|file_stem| file_stem.to_string_lossy().into_owned()).unwrap_or_default());
+    Some(LibraryTrack { path, title, artist: tags.artist, album: tags.album, track_number: tags.track_number, duration: tags.duration })
+}
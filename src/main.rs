@@ -5,6 +5,21 @@ pub mod convert_query;
 pub mod metadata;
 pub mod utils;
 pub mod data;
+pub mod http_stream;
+pub mod guild_state;
+pub mod audio_processing;
+pub mod permissions;
+pub mod settings;
+pub mod queue_persistence;
+pub mod shutdown;
+pub mod storage;
+pub mod playlists;
+pub mod queue_export;
+pub mod audio_probe;
+pub mod library;
+pub mod radio;
+pub mod track_matching;
+pub mod url_guard;
 
 use commands::error::CommandError;
 use error::{DynError, AppError};
@@ -26,9 +41,20 @@ async fn main() -> Result<(), DynError> {
     env_logger::init();
     
     let youtube_client = api_integration::youtube::YouTubeClient::new().await?;
-    let spotify_client = api_integration::spotify::SpotifyClient::new()?;
+    let spotify_client = api_integration::spotify::SpotifyClient::new().await?;
+
+    let settings_path = std::env::var("SETTINGS_PATH").unwrap_or(settings::DEFAULT_SETTINGS_PATH.to_owned());
+    let guild_states = guild_state::GuildStates::load(settings_path.into()).await?;
+    let queues_path = std::env::var("QUEUES_PATH").unwrap_or(queue_persistence::DEFAULT_QUEUES_PATH.to_owned());
+    let queue_store = queue_persistence::QueueStore::new(queues_path.into());
+    let playlists_path = std::env::var("PLAYLISTS_PATH").unwrap_or(playlists::DEFAULT_PLAYLISTS_PATH.to_owned());
+    let playlist_store = playlists::PlaylistStore::new(playlists_path.into());
+    let library = library::Library::new(std::env::var("LIBRARY_PATH").ok().map(|path| path.into())); // disabled if unset
 
     let token = std::env::var("DISCORD_TOKEN").map_err(|_| AppError::EnvVarsMissing { var: vec!["DISCORD_TOKEN".to_string()] })?;
+    let data = Data::new(spotify_client, youtube_client, guild_states, queue_store, playlist_store, library);
+    let (shutdown_guild_states, shutdown_queue_store, shutdown_cleanups) = (data.guild_states.clone(), data.queue_store.clone(), data.cleanups.clone());
+
     let intents = GatewayIntents::GUILD_MESSAGES | GatewayIntents::MESSAGE_CONTENT
                                 | GatewayIntents::GUILD_VOICE_STATES | GatewayIntents::GUILD_MEMBERS
                                 | GatewayIntents::DIRECT_MESSAGES | GatewayIntents::GUILD_PRESENCES
@@ -37,6 +63,8 @@ async fn main() -> Result<(), DynError> {
         .options(poise::FrameworkOptions { 
             commands: vec![
                 commands::play::play(),
+                commands::play::playnext(),
+                commands::play::playnow(),
                 commands::queue::queue(),
                 commands::song::song(),
                 commands::leave::leave(),
@@ -48,28 +76,91 @@ async fn main() -> Result<(), DynError> {
                 commands::stop::stop(),
                 commands::register::register(),
                 commands::help::help(),
-                commands::join::join()
+                commands::join::join(),
+                commands::seek::seek(),
+                commands::shuffle::shuffle(),
+                commands::remove::remove(),
+                commands::_move::_move(),
+                commands::jump::jump(),
+                commands::clear::clear(),
+                commands::previous::previous(),
+                commands::replay::replay(),
+                commands::history::history(),
+                commands::volume::volume(),
+                commands::normalize::normalize(),
+                commands::filter::filter(),
+                commands::voteskip::voteskip(),
+                commands::config::config(),
+                commands::playlist::playlist(),
+                commands::export::export(),
+                commands::library::library(),
+                commands::podcast::podcast(),
+                commands::search::search()
             ],
-            prefix_options: poise::PrefixFrameworkOptions { prefix: Some("-".to_owned()), ..Default::default() },
+            prefix_options: poise::PrefixFrameworkOptions { dynamic_prefix: Some(|ctx| Box::pin(dynamic_prefix(ctx))), ..Default::default() },
+            command_check: Some(|ctx| Box::pin(permissions::check_permissions(ctx))),
             post_command: |ctx| Box::pin(post_command(ctx)),
             on_error: |err| Box::pin(on_error(err)),
             event_handler: |ctx, event, framework_ctx, data| Box::pin(event_handler(ctx, event, framework_ctx, data)),
             ..Default::default()})
         .initialize_owners(true)
-        .setup(|_, ready, _| {
+        .setup(|ctx, ready, _framework| {
             Box::pin(async move {
                 println!("{} Has Connected To Discord", ready.user.tag());
-                Ok(Data::new(spotify_client, youtube_client))
+
+                let library = data.library.clone();
+                tokio::spawn(async move {
+                    match library.scan().await {
+                        Ok(tracks_len) => log::info!("indexed {} library tracks", tracks_len),
+                        Err(library::LibraryError::Disabled) => (),
+                        Err(err) => log::error!("{}", err)
+                    }
+                });
+
+                // queues saved before the restart are only restored once someone agrees to it
+                for (guild_id, saved_queue) in data.queue_store.load_all().await {
+                    let global_events = commands::utils::GlobalEvents {
+                        guild_id,
+                        channel_id: serenity::all::ChannelId::new(saved_queue.text_channel),
+                        http: ctx.http.clone(),
+                        guild_states: data.guild_states.clone(),
+                        client: data.reqwest_client.clone(),
+                        queue_store: data.queue_store.clone()
+                    };
+                    tokio::spawn(queue_persistence::offer_restore(ctx.clone(), global_events, saved_queue));
+                }
+                Ok(data)
             })
         })
         .build();
 
-    let mut client = poise::serenity_prelude::ClientBuilder::new(token, intents).register_songbird().framework(framework).await?;
+    let songbird = songbird::Songbird::serenity();
+    let mut client = poise::serenity_prelude::ClientBuilder::new(token, intents).register_songbird_with(songbird.clone()).framework(framework).await?;
+
+    // installed before connecting, a signal during startup would otherwise kill the process without the shutdown steps
+    tokio::spawn(shutdown::Shutdown {
+        http: client.http.clone(),
+        songbird,
+        shard_manager: client.shard_manager.clone(),
+        guild_states: shutdown_guild_states,
+        queue_store: shutdown_queue_store,
+        cleanups: shutdown_cleanups
+    }.on_signal());
     client.start().await?;
     Ok(())
 }
 
+async fn dynamic_prefix<'a>(ctx: poise::PartialContext<'a, Data, CommandError>) -> Result<Option<String>, CommandError> {
+    let Some(guild_id) = ctx.guild_id else { return Ok(Some(settings::DEFAULT_PREFIX.to_owned())); };
+    Ok(Some(ctx.data.guild_states.with(guild_id.get(), |state| state.settings.prefix.clone()).await))
+}
+
 async fn post_command<'a>(ctx: Context<'a>) {
+    let changes_queue = queue_persistence::changes_queue(&ctx.command().qualified_name);
+    if let (true, Some(guild_id), Some(manager)) = (changes_queue, ctx.guild_id(), songbird::get(ctx.serenity_context()).await) {
+        queue_persistence::persist_queue(&ctx.data().queue_store, manager.get(guild_id), &ctx.data().guild_states, guild_id.get()).await;
+    }
+
     let mut cleanups = ctx.data().cleanups.lock().await.clone();
     cleanups.sort_by(|a, b| b.delay.cmp(&a.delay));
     let mut time_slept = std::time::Duration::ZERO;
@@ -77,6 +168,7 @@ async fn post_command<'a>(ctx: Context<'a>) {
         tokio::time::sleep(cleanup.delay - time_slept).await;
         time_slept = cleanup.delay;
         let _ = cleanup.message.delete(&ctx.serenity_context().http).await;
+        ctx.data().cleanups.lock().await.retain(|pending| pending.message.id != cleanup.message.id);
     }
 }
 
@@ -91,6 +183,13 @@ async fn on_error<'a>(err: FrameworkError<'a, Data, CommandError>) {
                 let _ = commands::utils::send_timed_error(&ctx, message, Some(std::time::Duration::from_secs(10))).await;
             }
         },
+        FrameworkError::CommandCheckFailed { error, ctx, .. } => {
+            let message = error.unwrap_or(CommandError::MissingPermissions).to_string();
+            let _ = commands::utils::send_timed_error(&ctx, message, Some(std::time::Duration::from_secs(10))).await;
+        },
+        FrameworkError::NotAnOwner { ctx, .. } => {
+            let _ = commands::utils::send_timed_error(&ctx, CommandError::MissingPermissions.to_string(), Some(std::time::Duration::from_secs(10))).await;
+        },
         _ => ()
     }
 }
@@ -116,8 +215,9 @@ async fn event_handler<'a>(ctx: &serenity::prelude::Context, event: &poise::sere
                         if guild_channel.kind == poise::serenity_prelude::ChannelType::Voice {
                             let members_in_voice = guild_channel.members(ctx).map(|v| v.iter().filter(|p| !p.user.bot).count()).unwrap_or(0);
                             if members_in_voice == 0 {
+                                let afk_timeout = data.guild_states.with(guild_id.get(), |state| state.settings.afk_timeout()).await;
                                 let abort_handle = tokio::task::spawn(async move {
-                                    tokio::time::sleep(std::time::Duration::from_secs(120)).await;
+                                    tokio::time::sleep(afk_timeout).await;
                                     let _ = manager.remove(guild_id).await;
                                 }).abort_handle();
 
@@ -1,15 +1,16 @@
-use std::{time::Duration, sync::Arc};
+use std::{time::Duration, sync::{Arc, PoisonError}};
 
-use crate::utils::{format_duration, create_now_playing_embed};
+use crate::{utils::{format_duration, create_now_playing_embed}, guild_state::GuildStates, api_integration::spotify::SpotifyTrackData};
 use serenity::{http::Http, builder::CreateMessage};
-use songbird::{tracks::TrackHandle, Call, EventContext};
+use songbird::{tracks::{Track, TrackHandle}, input::Input, Call, EventContext};
 use poise::{ async_trait, serenity_prelude::{User, ChannelId} };
 use tokio::sync::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
 use thiserror::Error as ThisError;
 use typemap::Key as TypeMapKey;
 use typemap::ShareMap;
 
-#[derive(Debug, Clone, Hash)]
+#[derive(Debug, Clone, Hash, Serialize, Deserialize)]
 pub struct TrackMetadata {
     pub video_metadata: VideoMetadata,
     pub added_by: UserMetadata
@@ -24,7 +25,7 @@ impl Default for TrackMetadata {
     }
 }
 
-#[derive(Debug, Clone, Hash)]
+#[derive(Debug, Clone, Hash, Serialize, Deserialize)]
 pub struct VideoMetadata {
     pub title: String,
     pub duration: std::time::Duration,
@@ -32,41 +33,40 @@ pub struct VideoMetadata {
 }
 
 impl VideoMetadata {
+    pub fn is_live(&self) -> bool {
+        matches!(self.audio_source, AudioSource::Radio { .. })
+    }
+
+    // radio streams show the song they're currently playing next to the station name
+    pub fn display_title(&self) -> String {
+        match &self.audio_source {
+            AudioSource::Radio { stream_title, .. } => match stream_title.get() {
+                Some(stream_title) => format!("{} - {}", self.title, stream_title),
+                None => self.title.clone()
+            },
+            _ => self.title.clone()
+        }
+    }
+
+    // live streams have no duration
+    pub fn formatted_duration(&self) -> String {
+        match self.is_live() {
+            true => "LIVE".to_owned(),
+            false => format_duration(self.duration, None)
+        }
+    }
+
     pub fn to_queue_string(&self, playtime: Option<Duration>, limit: Option<usize>) -> String {
-        let mut formatted_duration = format_duration(self.duration, None);
-        if let Some(playtime) = playtime {
+        let mut formatted_duration = self.formatted_duration();
+        if let Some(playtime) = playtime.filter(|_| !self.is_live()) {
             let formatted_playtime = format_duration(playtime, Some(formatted_duration.len()));
             formatted_duration = format!("{} / {}", formatted_playtime, formatted_duration);
         }
         match &self.audio_source {
-            AudioSource::YouTube { video_id } => {
-                let mut queue_string = format!("[{}](https://youtu.be/{}) | {}", self.title, video_id, formatted_duration);
-
-                if let Some(limit) = limit {
-                    if queue_string.len() <= limit { return queue_string; }
-                    let mut truncated_title = self.title.clone();
-                    truncated_title.truncate(self.title.len()- (queue_string.len() - limit) - 3);
-                    truncated_title.push_str("...");
-
-                    queue_string = format!("[{}](https://youtu.be/{}) | {}", truncated_title, video_id, formatted_duration);
-                }
-
-                queue_string
-            },
-            AudioSource::File { path: _ } => {
-                let mut queue_string = format!("{} | {}", self.title, formatted_duration);
-
-                if let Some(limit) = limit {
-                    if queue_string.len() <= limit { return queue_string; }
-                    let mut truncated_title = self.title.clone();
-                    truncated_title.truncate(self.title.len()- (queue_string.len() - limit) - 3);
-                    truncated_title.push_str("...");
-                    
-                    queue_string = format!("{} | {}", self.title, formatted_duration);
-                }
-
-                queue_string
-            },
+            AudioSource::YouTube { video_id } => fit_queue_string(&self.title, Some(&format!("https://youtu.be/{}", video_id)), &formatted_duration, limit),
+            AudioSource::Url { url } | AudioSource::SoundCloud { url, .. } | AudioSource::Bandcamp { url } => fit_queue_string(&self.title, Some(url), &formatted_duration, limit),
+            AudioSource::Radio { url, .. } => fit_queue_string(&self.display_title(), Some(url), &formatted_duration, limit),
+            AudioSource::File { path: _ } => fit_queue_string(&self.title, None, &formatted_duration, limit),
             AudioSource::Jeja { .. } => {
                 self.title.clone()
             }
@@ -74,7 +74,7 @@ impl VideoMetadata {
     }
 }
 
-#[derive(Debug, Clone, Hash)]
+#[derive(Debug, Clone, Hash, Serialize, Deserialize)]
 pub struct UserMetadata {
     pub id: u64,
     pub name: String,
@@ -111,6 +111,13 @@ impl TypeMapKey for UserMetadata {
     type Value = UserMetadata;
 }
 
+// kept next to the query of tracks which came from spotify so the search can be matched against it
+pub struct SpotifyTrack(pub SpotifyTrackData);
+
+impl TypeMapKey for SpotifyTrack {
+    type Value = SpotifyTrack;
+}
+
 #[async_trait]
 pub trait LazyMetadata {
     async fn read_lazy_metadata(&self) -> Option<TrackMetadata>;
@@ -123,6 +130,8 @@ pub trait LazyMetadata {
     async fn is_awake(&self) -> bool;
     async fn read_query(&self) -> Option<String>;
     async fn write_query(&mut self, query: String);
+    async fn read_spotify_track(&self) -> Option<SpotifyTrackData>;
+    async fn write_spotify_track(&mut self, track_data: SpotifyTrackData);
 }
 
 #[derive(Debug, ThisError)]
@@ -158,7 +167,8 @@ impl LazyMetadata for TrackHandle {
     async fn generate_lazy_metadata(&mut self) -> Result<TrackMetadata, MetadataError> {
         let query = self.read_query().await.ok_or(MetadataError::MissingQuery)?;
         let added_by = self.read_added_by().await.ok_or(MetadataError::MissingAddedBy)?;
-        let video_metadata = crate::scrapers::youtube::search(&query).await?;
+        let spotify_track = self.read_spotify_track().await;
+        let video_metadata = crate::track_matching::resolve_query(&query, spotify_track.as_ref()).await?;
         Ok(TrackMetadata { video_metadata, added_by })
     }
 
@@ -204,37 +214,154 @@ impl LazyMetadata for TrackHandle {
     async fn write_query(&mut self, query: String) {
         self.data::<RwLock<ShareMap>>().write().await.insert::<Query>(Query(query));
     }
+
+    async fn read_spotify_track(&self) -> Option<SpotifyTrackData> {
+        self.data::<RwLock<ShareMap>>().read().await.get::<SpotifyTrack>().map(|spotify_track| spotify_track.0.clone())
+    }
+
+    async fn write_spotify_track(&mut self, track_data: SpotifyTrackData) {
+        self.data::<RwLock<ShareMap>>().write().await.insert::<SpotifyTrack>(SpotifyTrack(track_data));
+    }
 }
 
 pub struct LazyMetadataEventHandler {
     pub handler: Arc<Mutex<Call>>,
     pub channel_id: ChannelId,
-    pub http: Arc<Http>
+    pub http: Arc<Http>,
+    pub guild_states: GuildStates,
+    pub guild_id: u64
 }
 
 #[async_trait]
 impl songbird::events::EventHandler for LazyMetadataEventHandler {
     async fn act(&self, ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
         let EventContext::Track(slice) = ctx else { return None; };
-        let Some((track_state, _)) = slice.get(0) else { return None; };
+        let Some((track_state, track_handle)) = slice.get(0) else { return None; };
         let Some(mut current_track) = ({ let handler_guard = self.handler.lock().await; handler_guard.queue().current() }) else { return None; }; // have to do this monstrosity to avoid mutex dead locking
        
         if track_state.play_time.as_secs() != 0 { return None; } ;
 
+        let settings = self.guild_states.with(self.guild_id, |state| state.settings.clone()).await;
+        let _ = track_handle.set_volume(settings.volume);
+        let filter = settings.audio_settings.read().unwrap_or_else(PoisonError::into_inner).filter;
+
         let Ok(track_metadata) = current_track.read_generate_lazy_metadata().await else { return None; };
+        self.guild_states.with(self.guild_id, |state| state.push_history(track_metadata.clone())).await;
 
-        let Ok(message) = self.channel_id.send_message(&self.http, CreateMessage::new().embed(create_now_playing_embed(track_metadata))).await else { return None; };
+        // announcements go to the channel the bot was summoned from unless configured otherwise
+        let channel_id = settings.announcement_channel.map(ChannelId::new).unwrap_or(self.channel_id);
+        let Ok(message) = channel_id.send_message(&self.http, CreateMessage::new().embed(create_now_playing_embed(track_metadata, filter))).await else { return None; };
         
-        tokio::time::sleep(Duration::from_secs(10)).await;
+        tokio::time::sleep(settings.announcement_cleanup_delay()).await;
         let _ = message.delete(&self.http).await;
         
         None
     }
 }
 
-#[derive(Debug, Clone, Hash)]
+// creates a track which plays the given metadata
+pub fn track_from_metadata(track_metadata: TrackMetadata, client: reqwest::Client) -> Track {
+    let input: Input = crate::convert_query::YouTubeComposer::Metadata { metadata: track_metadata.video_metadata.clone(), client }.into();
+    let mut share_map = ShareMap::custom();
+    share_map.insert::<TrackMetadata>(track_metadata);
+    Track::new_with_data(input, Arc::new(RwLock::new(share_map)))
+}
+
+// creates a new track with a fresh input out of the data of an already used track handle
+pub async fn rebuild_track(track_handle: &TrackHandle, client: reqwest::Client) -> Option<Track> {
+    let data = track_handle.data::<RwLock<ShareMap>>();
+    let data_guard = data.read().await;
+
+    if let Some(track_metadata) = data_guard.get::<TrackMetadata>() {
+        return Some(track_from_metadata(track_metadata.clone(), client));
+    }
+
+    let query = data_guard.get::<Query>()?.0.clone();
+    let added_by = data_guard.get::<UserMetadata>()?.clone();
+    let spotify_track = data_guard.get::<SpotifyTrack>().map(|spotify_track| spotify_track.0.clone());
+    Some(track_from_query(query, added_by, spotify_track, client))
+}
+
+// creates a track whose metadata gets generated once it's needed
+pub fn track_from_query(query: String, added_by: UserMetadata, spotify_track: Option<SpotifyTrackData>, client: reqwest::Client) -> Track {
+    let mut share_map = ShareMap::custom();
+    share_map.insert::<UserMetadata>(added_by);
+    share_map.insert::<Query>(Query(query.clone()));
+    if let Some(track_data) = spotify_track.clone() {
+        share_map.insert::<SpotifyTrack>(SpotifyTrack(track_data));
+    }
+    let input: Input = crate::convert_query::YouTubeComposer::Query { query, spotify_track, client }.into();
+    Track::new_with_data(input, Arc::new(RwLock::new(share_map)))
+}
+
+// the least of the title which has to fit next to a link, otherwise the link is left out
+const MIN_QUEUE_TITLE_LENGTH: usize = 16;
+
+fn format_queue_string(title: &str, link: Option<&str>, formatted_duration: &str) -> String {
+    match link {
+        Some(link) => format!("[{}]({}) | {}", title, link, formatted_duration),
+        None => format!("{} | {}", title, formatted_duration)
+    }
+}
+
+// fits a queue line into `limit` bytes, titles often aren't ascii so the cut has to land on a char boundary
+fn fit_queue_string(title: &str, link: Option<&str>, formatted_duration: &str, limit: Option<usize>) -> String {
+    let queue_string = format_queue_string(title, link, formatted_duration);
+    let Some(limit) = limit else { return queue_string; };
+    if queue_string.len() <= limit { return queue_string; }
+
+    // long links (e.g. discord attachments) can take up the whole line by themselves
+    let link = link.filter(|link| format_queue_string("", Some(link), formatted_duration).len() + MIN_QUEUE_TITLE_LENGTH <= limit);
+    let queue_string = format_queue_string(title, link, formatted_duration);
+    if queue_string.len() <= limit { return queue_string; }
+
+    let mut cut = limit.saturating_sub(format_queue_string("...", link, formatted_duration).len()).min(title.len());
+    while !title.is_char_boundary(cut) { cut -= 1; }
+    format_queue_string(&format!("{}...", &title[..cut]), link, formatted_duration)
+}
+
+#[derive(Debug, Clone, Hash, Serialize, Deserialize)]
 pub enum AudioSource {
     YouTube { video_id: String },
     File { path: std::path::PathBuf },
+    Url { url: String },
+    SoundCloud { track_id: u64, url: String },
+    Bandcamp { url: String }, // page of the track, the stream url expires
+    Radio { url: String, #[serde(skip)] stream_title: StreamTitle },
     Jeja { filename: String }
 }
+
+impl AudioSource {
+    // links the track is requested from, youtube videos are looked up by id instead
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            AudioSource::Url { url } | AudioSource::Radio { url, .. } | AudioSource::SoundCloud { url, .. } | AudioSource::Bandcamp { url } => Some(url),
+            AudioSource::YouTube { .. } | AudioSource::File { .. } | AudioSource::Jeja { .. } => None
+        }
+    }
+
+    // discord attachment links stop working after about a day, so they aren't saved anywhere
+    pub fn is_expiring(&self) -> bool {
+        let AudioSource::Url { url } = self else { return false; };
+        url::Url::parse(url).is_ok_and(|url| matches!(url.host_str(), Some("cdn.discordapp.com" | "media.discordapp.net")))
+    }
+}
+
+// current song of a radio stream, shared by the stream reading it and everything displaying the track
+#[derive(Debug, Clone, Default)]
+pub struct StreamTitle(Arc<std::sync::RwLock<Option<String>>>);
+
+impl StreamTitle {
+    pub fn get(&self) -> Option<String> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    pub fn set(&self, title: String) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = Some(title);
+    }
+}
+
+// the title changes while the stream plays, so it isn't part of the source's identity
+impl std::hash::Hash for StreamTitle {
+    fn hash<H: std::hash::Hasher>(&self, _state: &mut H) {}
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use crate::{data::Context, commands::error::CommandError};
+use poise::serenity_prelude::Guild;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, poise::ChoiceParameter)]
+pub enum PermissionLevel {
+    Everyone,
+    #[name = "DJ"]
+    Dj,
+    Admin
+}
+
+// commands which aren't listed are open to everyone
+const DEFAULT_POLICY: &[(&str, PermissionLevel)] = &[
+    ("stop", PermissionLevel::Dj),
+    ("leave", PermissionLevel::Dj),
+    ("clear", PermissionLevel::Dj),
+    ("volume", PermissionLevel::Dj),
+    ("filter", PermissionLevel::Dj),
+    ("normalize", PermissionLevel::Dj),
+    ("move", PermissionLevel::Dj),
+    ("remove", PermissionLevel::Dj),
+    ("jump", PermissionLevel::Dj),
+    ("shuffle", PermissionLevel::Dj),
+    ("voteskip", PermissionLevel::Admin),
+    ("config", PermissionLevel::Admin)
+];
+
+pub fn default_policy() -> HashMap<String, PermissionLevel> {
+    DEFAULT_POLICY.iter().map(|(command, level)| (command.to_string(), *level)).collect()
+}
+
+// members who can manage the server are admins, the dj role has to be configured per guild
+pub async fn member_permission_level(ctx: &Context<'_>, guild: &Guild) -> PermissionLevel {
+    let Some(member) = ctx.author_member().await else { return PermissionLevel::Everyone; };
+    if guild.member_permissions(&member).manage_guild() { return PermissionLevel::Admin; }
+
+    let dj_role = ctx.data().guild_states.with(guild.id.get(), |state| state.settings.dj_role).await;
+    match dj_role {
+        Some(dj_role) if member.roles.iter().any(|role| role.get() == dj_role) => PermissionLevel::Dj,
+        _ => PermissionLevel::Everyone
+    }
+}
+
+// used as the framework's command check, subcommands share the policy of their parent
+pub async fn check_permissions(ctx: Context<'_>) -> Result<bool, CommandError> {
+    let Some(guild) = ctx.guild().map(|guild| guild.clone()) else { return Ok(true); };
+    let command = ctx.command().qualified_name.split(' ').next().unwrap_or_default().to_owned();
+
+    let required = ctx.data().guild_states.with(guild.id.get(), |state| {
+        state.settings.permissions.get(&command).copied().unwrap_or(PermissionLevel::Everyone)
+    }).await;
+
+    Ok(has_permission(&ctx, &guild, required).await)
+}
+
+pub async fn has_permission(ctx: &Context<'_>, guild: &Guild, required: PermissionLevel) -> bool {
+    if required == PermissionLevel::Everyone { return true; }
+
+    // without a dj role there is nobody to restrict dj commands to
+    let dj_role = ctx.data().guild_states.with(guild.id.get(), |state| state.settings.dj_role).await;
+    if required == PermissionLevel::Dj && dj_role.is_none() { return true; }
+
+    member_permission_level(ctx, guild).await >= required
+}
@@ -0,0 +1,71 @@
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
+
+use crate::{metadata::{AudioSource, LazyMetadata, TrackMetadata, VideoMetadata}, storage::{read_json, write_json, StorageError}};
+use serde::{Deserialize, Serialize};
+use songbird::tracks::TrackHandle;
+use tokio::sync::Mutex;
+
+pub const DEFAULT_PLAYLISTS_PATH: &str = "playlists";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, poise::ChoiceParameter)]
+pub enum PlaylistScope {
+    #[default]
+    Personal,
+    #[name = "Server"]
+    Guild
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PlaylistEntry {
+    Track(VideoMetadata),
+    Query(String) // converted on every load, so links to spotify playlists stay up to date
+}
+
+pub type Playlists = BTreeMap<String, Vec<PlaylistEntry>>;
+
+// jokes are skipped, they're generated into a single file per guild which gets overwritten, and so are expiring attachment links
+pub async fn entries_from_queue(track_handles: Vec<TrackHandle>) -> Vec<PlaylistEntry> {
+    let mut entries = vec![];
+    for track_handle in track_handles {
+        match track_handle.read_lazy_metadata().await {
+            Some(TrackMetadata { video_metadata, .. }) if matches!(video_metadata.audio_source, AudioSource::Jeja { .. }) || video_metadata.audio_source.is_expiring() => (),
+            Some(track_metadata) => entries.push(PlaylistEntry::Track(track_metadata.video_metadata)),
+            None => entries.extend(track_handle.read_query().await.map(PlaylistEntry::Query))
+        }
+    }
+    entries
+}
+
+// one file per user or guild, holding all of their playlists
+#[derive(Debug, Clone)]
+pub struct PlaylistStore {
+    path: Arc<PathBuf>,
+    write_lock: Arc<Mutex<()>>
+}
+
+impl PlaylistStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path: Arc::new(path), write_lock: Arc::new(Mutex::new(())) }
+    }
+
+    fn owner_path(&self, scope: PlaylistScope, owner_id: u64) -> PathBuf {
+        let directory = match scope {
+            PlaylistScope::Personal => "users",
+            PlaylistScope::Guild => "guilds"
+        };
+        self.path.join(directory).join(format!("{}.json", owner_id))
+    }
+
+    pub async fn read(&self, scope: PlaylistScope, owner_id: u64) -> Result<Playlists, StorageError> {
+        Ok(read_json(&self.owner_path(scope, owner_id)).await?.unwrap_or_default())
+    }
+
+    pub async fn update<T>(&self, scope: PlaylistScope, owner_id: u64, func: impl FnOnce(&mut Playlists) -> T) -> Result<T, StorageError> {
+        let _write_guard = self.write_lock.lock().await;
+        let path = self.owner_path(scope, owner_id);
+        let mut playlists = read_json::<Playlists>(&path).await?.unwrap_or_default();
+        let result = func(&mut playlists);
+        write_json(&path, &playlists).await?;
+        Ok(result)
+    }
+}
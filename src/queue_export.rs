@@ -0,0 +1,218 @@
+use std::time::Duration;
+
+use crate::{audio_probe::is_audio_url, metadata::{AudioSource, VideoMetadata}, playlists::PlaylistEntry};
+use serde::{Deserialize, Serialize};
+use thiserror::Error as ThisError;
+use url::Url;
+
+pub const MAX_IMPORT_SIZE: u32 = 1024 * 1024;
+// every query is searched for one after another, so long files would keep the command running for ages
+pub const MAX_IMPORT_ENTRIES: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, poise::ChoiceParameter)]
+pub enum ExportFormat {
+    #[default]
+    #[name = "JSON"]
+    Json,
+    #[name = "M3U"]
+    M3u
+}
+
+impl ExportFormat {
+    pub fn filename(&self) -> &'static str {
+        match self {
+            Self::Json => "queue.json",
+            Self::M3u => "queue.m3u"
+        }
+    }
+}
+
+#[derive(Debug, ThisError)]
+pub enum ImportError {
+    #[error("Queue files can't be larger than 1 MB")]
+    TooLarge,
+    #[error("Queue files can't have more than 500 entries")]
+    TooManyEntries,
+    #[error("Only audio files and .json or .m3u queue files are supported")]
+    UnknownFormat,
+    #[error("The queue file is invalid")]
+    Invalid
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ExportedQueue {
+    tracks: Vec<PlaylistEntry>
+}
+
+pub fn export_queue(entries: Vec<PlaylistEntry>, format: ExportFormat) -> Vec<u8> {
+    let entries = entries.into_iter().map(hide_local_path).collect::<Vec<PlaylistEntry>>();
+    match format {
+        ExportFormat::Json => serde_json::to_vec_pretty(&ExportedQueue { tracks: entries }).unwrap_or_default(),
+        ExportFormat::M3u => to_m3u(&entries).into_bytes()
+    }
+}
+
+// the path on the host isn't shared, local files are searched for in the library again instead
+fn hide_local_path(entry: PlaylistEntry) -> PlaylistEntry {
+    match entry {
+        PlaylistEntry::Track(VideoMetadata { title, audio_source: AudioSource::File { .. }, .. }) => PlaylistEntry::Query(format!("{}{}", crate::library::LOCAL_PREFIX, single_line(&title))),
+        entry => entry
+    }
+}
+
+// the format is picked by the file extension, falling back to the content
+pub fn import_queue(filename: &str, content: &[u8]) -> Result<Vec<PlaylistEntry>, ImportError> {
+    let extension = filename.rsplit_once('.').map(|(_, extension)| extension.to_lowercase());
+    let content = std::str::from_utf8(content).map_err(|_| ImportError::Invalid)?;
+
+    let entries = match extension.as_deref() {
+        Some("json") => from_json(content)?,
+        Some("m3u") | Some("m3u8") => from_m3u(content),
+        _ if content.trim_start().starts_with('{') => from_json(content)?,
+        _ if content.trim_start().starts_with("#EXTM3U") => from_m3u(content),
+        _ => return Err(ImportError::UnknownFormat)
+    };
+    if entries.len() > MAX_IMPORT_ENTRIES { return Err(ImportError::TooManyEntries); }
+    Ok(entries)
+}
+
+// entries pointing at local files are dropped, the file could be anything on the host
+fn from_json(content: &str) -> Result<Vec<PlaylistEntry>, ImportError> {
+    let exported_queue = serde_json::from_str::<ExportedQueue>(content).map_err(|_| ImportError::Invalid)?;
+    Ok(exported_queue.tracks.into_iter().filter(|entry| match entry {
+        PlaylistEntry::Track(video_metadata) => matches!(video_metadata.audio_source, AudioSource::YouTube { .. } | AudioSource::Url { .. } | AudioSource::Radio { .. } | AudioSource::SoundCloud { .. } | AudioSource::Bandcamp { .. }),
+        PlaylistEntry::Query(_) => true
+    }).collect())
+}
+
+fn to_m3u(entries: &[PlaylistEntry]) -> String {
+    let mut m3u = String::from("#EXTM3U\n");
+    for entry in entries {
+        match entry {
+            PlaylistEntry::Track(video_metadata) => {
+                let location = match &video_metadata.audio_source {
+                    AudioSource::YouTube { video_id } => format!("https://youtu.be/{}", video_id),
+                    AudioSource::File { .. } => continue, // replaced with a library query before
+                    AudioSource::Url { url } | AudioSource::Radio { url, .. } | AudioSource::SoundCloud { url, .. } | AudioSource::Bandcamp { url } => url.clone(),
+                    AudioSource::Jeja { .. } => continue
+                };
+                let duration = match video_metadata.is_live() {
+                    true => -1,
+                    false => video_metadata.duration.as_secs() as i64
+                };
+                m3u.push_str(&format!("#EXTINF:{},{}\n{}\n", duration, single_line(&video_metadata.title), location));
+            },
+            // -1 marks an unknown duration
+            PlaylistEntry::Query(query) => m3u.push_str(&format!("#EXTINF:-1,{}\n{}\n", single_line(query), single_line(query)))
+        }
+    }
+    m3u
+}
+
+// youtube and direct links with an #EXTINF line are restored without looking them up again, everything else becomes a query
+fn from_m3u(content: &str) -> Vec<PlaylistEntry> {
+    let mut entries = vec![];
+    let mut extinf = None;
+    for line in content.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            extinf = info.split_once(',').map(|(duration, title)| (duration.trim().parse::<i64>().ok(), title.trim().to_owned()));
+            continue;
+        }
+        if line.starts_with('#') { continue; }
+
+        let entry = match (extinf.take(), audio_source(line)) {
+            (Some((Some(duration), title)), Some(audio_source)) if duration >= 0 && !title.is_empty() => PlaylistEntry::Track(VideoMetadata {
+                title,
+                duration: Duration::from_secs(duration as u64),
+                audio_source
+            }),
+            _ => PlaylistEntry::Query(line.to_owned())
+        };
+        entries.push(entry);
+    }
+    entries
+}
+
+fn audio_source(location: &str) -> Option<AudioSource> {
+    let url = Url::parse(location).ok()?;
+    let video_id = match url.domain()? {
+        "www.youtu.be" | "youtu.be" => url.path().strip_prefix('/').filter(|video_id| !video_id.is_empty()).map(|video_id| video_id.to_owned()),
+        "www.youtube.com" | "youtube.com" => url.query_pairs().find(|(key, _)| key == "v").map(|(_, video_id)| video_id.into_owned()),
+        _ if is_audio_url(&url) => return Some(AudioSource::Url { url: location.to_owned() }),
+        _ => None
+    };
+    video_id.map(|video_id| AudioSource::YouTube { video_id })
+}
+
+fn single_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<PlaylistEntry> {
+        vec![
+            PlaylistEntry::Track(VideoMetadata { title: "Never Gonna Give You Up".to_owned(), duration: Duration::from_secs(213), audio_source: AudioSource::YouTube { video_id: "dQw4w9WgXcQ".to_owned() } }),
+            PlaylistEntry::Track(VideoMetadata { title: "song".to_owned(), duration: Duration::from_secs(95), audio_source: AudioSource::Url { url: "https://example.com/song.mp3".to_owned() } }),
+            PlaylistEntry::Track(VideoMetadata { title: "Secret\nDemo".to_owned(), duration: Duration::from_secs(60), audio_source: AudioSource::File { path: "/home/diwa/music/demo.flac".into() } }),
+            PlaylistEntry::Query("daft punk one more time".to_owned())
+        ]
+    }
+
+    #[test]
+    fn m3u_round_trip() {
+        let exported = String::from_utf8(export_queue(entries(), ExportFormat::M3u)).unwrap();
+        assert!(!exported.contains("/home/diwa"));
+
+        let imported = import_queue("queue.m3u", exported.as_bytes()).unwrap();
+        assert_eq!(imported.len(), 4);
+        assert!(matches!(&imported[0], PlaylistEntry::Track(VideoMetadata { title, duration, audio_source: AudioSource::YouTube { video_id } })
+            if title == "Never Gonna Give You Up" && duration.as_secs() == 213 && video_id == "dQw4w9WgXcQ"));
+        assert!(matches!(&imported[1], PlaylistEntry::Track(VideoMetadata { duration, audio_source: AudioSource::Url { url }, .. })
+            if duration.as_secs() == 95 && url == "https://example.com/song.mp3"));
+        assert!(matches!(&imported[2], PlaylistEntry::Query(query) if query == "local:Secret Demo"));
+        assert!(matches!(&imported[3], PlaylistEntry::Query(query) if query == "daft punk one more time"));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let exported = export_queue(entries(), ExportFormat::Json);
+        assert!(!String::from_utf8_lossy(&exported).contains("/home/diwa"));
+
+        let imported = import_queue("queue.json", &exported).unwrap();
+        assert_eq!(imported.len(), 4);
+        assert!(matches!(&imported[0], PlaylistEntry::Track(VideoMetadata { audio_source: AudioSource::YouTube { video_id }, .. }) if video_id == "dQw4w9WgXcQ"));
+        assert!(matches!(&imported[2], PlaylistEntry::Query(query) if query == "local:Secret Demo"));
+    }
+
+    #[test]
+    fn json_import_drops_local_files() {
+        let content = r#"{"tracks":[{"Track":{"title":"passwd","duration":{"secs":1,"nanos":0},"audio_source":{"File":{"path":"/etc/passwd"}}}},{"Query":"some song"}]}"#;
+        let imported = from_json(content).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert!(matches!(&imported[0], PlaylistEntry::Query(query) if query == "some song"));
+    }
+
+    #[test]
+    fn m3u_without_extinf_becomes_queries() {
+        let imported = from_m3u("#EXTM3U\nhttps://youtu.be/dQw4w9WgXcQ\n\n#EXTINF:-1,Radio\nhttp://example.com/stream.mp3\n");
+        assert_eq!(imported.len(), 2);
+        assert!(imported.iter().all(|entry| matches!(entry, PlaylistEntry::Query(_))));
+    }
+
+    #[test]
+    fn rejects_files_with_too_many_entries() {
+        let content = format!("#EXTM3U\n{}", "some song\n".repeat(MAX_IMPORT_ENTRIES + 1));
+        assert!(matches!(import_queue("queue.m3u", content.as_bytes()), Err(ImportError::TooManyEntries)));
+        let content = format!("#EXTM3U\n{}", "some song\n".repeat(MAX_IMPORT_ENTRIES));
+        assert_eq!(import_queue("queue.m3u", content.as_bytes()).unwrap().len(), MAX_IMPORT_ENTRIES);
+    }
+
+    #[test]
+    fn rejects_unknown_files() {
+        assert!(matches!(import_queue("notes.txt", b"hello"), Err(ImportError::UnknownFormat)));
+        assert!(matches!(import_queue("queue.json", b"{not json"), Err(ImportError::Invalid)));
+    }
+}
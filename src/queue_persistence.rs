@@ -0,0 +1,240 @@
+use std::{path::PathBuf, sync::{atomic::{AtomicBool, Ordering}, Arc}, time::Duration};
+
+use crate::{api_integration::spotify::SpotifyTrackData, audio_processing::process_track, guild_state::{GuildStates, LoopMode}, metadata::{track_from_metadata, track_from_query, AudioSource, LazyMetadata, TrackMetadata, UserMetadata}, storage::{read_json, remove_json, write_json, StorageError}};
+use crate::commands::{_loop::set_loop_mode, error::{CommandError, VoiceError}, utils::GlobalEvents};
+use poise::async_trait;
+use serde::{Deserialize, Serialize};
+use serenity::{all::{ButtonStyle, ChannelId, GuildId}, builder::{CreateActionRow, CreateButton, CreateEmbed, CreateMessage}, model::Color};
+use songbird::{Call, tracks::{LoopState, Track}};
+use tokio::sync::Mutex;
+
+pub const DEFAULT_QUEUES_PATH: &str = "queues";
+const RESTORE_OFFER_TIMEOUT: Duration = Duration::from_secs(600);
+// commands which can't change the queue, saving it after them would only rewrite the same file
+const READ_ONLY_COMMANDS: [&str; 15] = [
+    "queue", "history", "help", "register", "export", "config", "volume", "normalize", "filter",
+    "playlist save", "playlist list", "playlist delete", "playlist add", "library search", "library rescan"
+];
+
+// subcommands of a read only command are read only too
+pub fn changes_queue(qualified_name: &str) -> bool {
+    !READ_ONLY_COMMANDS.iter().any(|name| qualified_name == *name || qualified_name.strip_prefix(name).is_some_and(|rest| rest.starts_with(' ')))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SavedTrack {
+    Metadata(TrackMetadata),
+    Query { query: String, added_by: UserMetadata, #[serde(default)] spotify_track: Option<SpotifyTrackData> }
+}
+
+impl SavedTrack {
+    pub fn into_track(self, client: reqwest::Client) -> Track {
+        match self {
+            Self::Metadata(track_metadata) => track_from_metadata(track_metadata, client),
+            Self::Query { query, added_by, spotify_track } => track_from_query(query, added_by, spotify_track, client)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedQueue {
+    pub voice_channel: u64,
+    pub text_channel: u64,
+    pub loop_mode: LoopMode,
+    pub track_loops: Option<usize>,
+    pub position: Duration, // of the first track
+    pub tracks: Vec<SavedTrack>
+}
+
+impl SavedQueue {
+    // None if there is nothing worth restoring
+    pub async fn capture(handler: &Arc<Mutex<Call>>, guild_states: &GuildStates, guild_id: u64) -> Option<Self> {
+        let (voice_channel, track_handles) = {
+            let handler_guard = handler.lock().await;
+            (handler_guard.current_channel()?.0.get(), handler_guard.queue().current_queue())
+        };
+        let (loop_mode, text_channel) = guild_states.with(guild_id, |state| (state.loop_mode, state.text_channel)).await;
+
+        let mut tracks = vec![];
+        let mut position = Duration::ZERO;
+        let mut track_loops = None;
+        for track_handle in track_handles {
+            // finished tracks stay in the queue until their end event is handled
+            let Ok(info) = track_handle.get_info().await else { continue; };
+
+            let saved_track = match track_handle.read_lazy_metadata().await {
+                // jokes are generated into a single file per guild which gets overwritten
+                Some(TrackMetadata { video_metadata, .. }) if matches!(video_metadata.audio_source, AudioSource::Jeja { .. }) => continue,
+                Some(TrackMetadata { video_metadata, .. }) if video_metadata.audio_source.is_expiring() => continue,
+                Some(track_metadata) => SavedTrack::Metadata(track_metadata),
+                None => {
+                    let (Some(query), Some(added_by)) = (track_handle.read_query().await, track_handle.read_added_by().await) else { continue; };
+                    SavedTrack::Query { query, added_by, spotify_track: track_handle.read_spotify_track().await }
+                }
+            };
+
+            if tracks.is_empty() {
+                position = info.position;
+                if let LoopState::Finite(count) = info.loops { track_loops = Some(count); }
+            }
+            tracks.push(saved_track);
+        }
+        if tracks.is_empty() { return None; }
+
+        Some(Self { voice_channel, text_channel: text_channel?, loop_mode, track_loops, position, tracks })
+    }
+}
+
+// one file per guild so guilds don't rewrite each other's queues
+#[derive(Debug, Clone)]
+pub struct QueueStore {
+    path: Arc<PathBuf>,
+    write_lock: Arc<Mutex<()>>,
+    frozen: Arc<AtomicBool>
+}
+
+impl QueueStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path: Arc::new(path), write_lock: Arc::new(Mutex::new(())), frozen: Arc::new(AtomicBool::new(false)) }
+    }
+
+    // stops all further writes, the saved queues stay as they are
+    pub fn freeze(&self) {
+        self.frozen.store(true, Ordering::SeqCst);
+    }
+
+    fn queue_path(&self, guild_id: u64) -> PathBuf {
+        self.path.join(format!("{}.json", guild_id))
+    }
+
+    pub async fn save(&self, guild_id: u64, saved_queue: &SavedQueue) -> Result<(), StorageError> {
+        let _write_guard = self.write_lock.lock().await;
+        if self.frozen.load(Ordering::SeqCst) { return Ok(()); }
+        write_json(&self.queue_path(guild_id), saved_queue).await
+    }
+
+    pub async fn remove(&self, guild_id: u64) -> Result<(), StorageError> {
+        let _write_guard = self.write_lock.lock().await;
+        if self.frozen.load(Ordering::SeqCst) { return Ok(()); }
+        remove_json(&self.queue_path(guild_id)).await
+    }
+
+    // unreadable queues are skipped
+    pub async fn load_all(&self) -> Vec<(u64, SavedQueue)> {
+        let mut saved_queues = vec![];
+        let Ok(mut entries) = tokio::fs::read_dir(self.path.as_ref()).await else { return saved_queues; };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("json") { continue; }
+            let Some(guild_id) = path.file_stem().and_then(|stem| stem.to_str()).and_then(|stem| stem.parse::<u64>().ok()) else { continue; };
+
+            match read_json::<SavedQueue>(&path).await {
+                Ok(Some(saved_queue)) => saved_queues.push((guild_id, saved_queue)),
+                Ok(None) => (),
+                Err(err) => log::error!("{}", err)
+            }
+        }
+        saved_queues
+    }
+}
+
+// saves the current queue of a guild, or removes the saved one once there's nothing left to restore
+pub async fn persist_queue(queue_store: &QueueStore, handler: Option<Arc<Mutex<Call>>>, guild_states: &GuildStates, guild_id: u64) {
+    let saved_queue = match handler {
+        Some(handler) => SavedQueue::capture(&handler, guild_states, guild_id).await,
+        None => None
+    };
+    let result = match saved_queue {
+        Some(saved_queue) => queue_store.save(guild_id, &saved_queue).await,
+        None => queue_store.remove(guild_id).await
+    };
+    if let Err(err) = result {
+        log::error!("{}", err);
+    }
+}
+
+pub struct QueuePersistenceEventHandler {
+    pub handler: Arc<Mutex<Call>>,
+    pub guild_states: GuildStates,
+    pub guild_id: u64,
+    pub queue_store: QueueStore
+}
+
+#[async_trait]
+impl songbird::events::EventHandler for QueuePersistenceEventHandler {
+    async fn act(&self, _ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+        persist_queue(&self.queue_store, Some(self.handler.clone()), &self.guild_states, self.guild_id).await;
+        None
+    }
+}
+
+// asks in the bound text channel whether the queue from before the restart should be restored
+pub async fn offer_restore(ctx: serenity::all::Context, global_events: GlobalEvents, saved_queue: SavedQueue) {
+    let guild_id = global_events.guild_id;
+    let queue_store = global_events.queue_store.clone();
+
+    let message = ChannelId::new(saved_queue.text_channel).send_message(&ctx.http, CreateMessage::new()
+        .embed(CreateEmbed::new()
+            .title("Restore Queue?")
+            .description(format!("{} tracks were queued in <#{}> before the restart", saved_queue.tracks.len(), saved_queue.voice_channel))
+            .color(Color::PURPLE))
+        .components(vec![CreateActionRow::Buttons(vec![
+            CreateButton::new("restore").label("Restore").style(ButtonStyle::Success),
+            CreateButton::new("discard").label("Discard").style(ButtonStyle::Danger)
+        ])])
+    ).await;
+    let Ok(message) = message else {
+        let _ = queue_store.remove(guild_id).await;
+        return;
+    };
+
+    let interaction = message.await_component_interaction(&ctx.shard).timeout(RESTORE_OFFER_TIMEOUT).await;
+    let _ = message.delete(&ctx.http).await;
+    match interaction {
+        Some(interaction) if interaction.data.custom_id == "restore" => {
+            if let Err(err) = restore_queue(&ctx, global_events, saved_queue).await {
+                log::error!("{}", err);
+            }
+        },
+        _ => {
+            let _ = queue_store.remove(guild_id).await;
+        }
+    }
+}
+
+async fn restore_queue(ctx: &serenity::all::Context, global_events: GlobalEvents, saved_queue: SavedQueue) -> Result<(), CommandError> {
+    let manager = songbird::get(ctx).await.ok_or(VoiceError::NoManager)?;
+    let guild_id = global_events.guild_id;
+    let guild_states = global_events.guild_states.clone();
+    let client = global_events.client.clone();
+
+    let handler = manager.get_or_insert(GuildId::new(guild_id));
+    let connection = handler.lock().await.current_connection().and_then(|conn| conn.channel_id);
+    let handler = manager.join(GuildId::new(guild_id), ChannelId::new(saved_queue.voice_channel)).await?;
+
+    let audio_settings = guild_states.with(guild_id, |state| state.settings.audio_settings.clone()).await;
+    let mut track_handles = vec![];
+    {
+        let mut handler_guard = handler.lock().await;
+
+        // add event handler upon joining a channel
+        if connection.is_none() {
+            global_events.register(&mut handler_guard, handler.clone()).await;
+        }
+
+        let _ = handler_guard.deafen(true).await;
+
+        for saved_track in saved_queue.tracks {
+            track_handles.push(handler_guard.enqueue(process_track(saved_track.into_track(client.clone()), &audio_settings)).await);
+        }
+    }
+
+    let first_track = track_handles.into_iter().next();
+    if let Some(first_track) = &first_track {
+        if !saved_queue.position.is_zero() {
+            let _ = first_track.seek(saved_queue.position); // the seek is applied once the track gets created
+        }
+    }
+    set_loop_mode(&guild_states, guild_id, first_track, saved_queue.loop_mode, saved_queue.track_loops).await
+}
@@ -0,0 +1,262 @@
+use std::{io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult, SeekFrom}, pin::Pin, task::{Context, Poll}};
+
+use crate::metadata::{AudioSource, StreamTitle, VideoMetadata};
+use futures::{Stream, StreamExt, TryStreamExt};
+use reqwest::{Client, header::{CONTENT_LENGTH, CONTENT_TYPE, HeaderMap}};
+use songbird::input::{AsyncAdapterStream, AsyncMediaSource, AudioStream, AudioStreamError};
+use symphonia::core::{io::MediaSource, probe::Hint};
+use thiserror::Error as ThisError;
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+use url::Url;
+
+pub const PLAYLIST_EXTENSIONS: [&str; 2] = ["pls", "m3u"];
+const MAX_PLAYLIST_SIZE: usize = 64 * 1024;
+
+type ByteStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>, reqwest::Error>> + Send + Sync>>;
+
+#[derive(Debug, ThisError)]
+pub enum RadioError {
+    #[error("Couldn't reach the radio stream")]
+    Request(#[from] reqwest::Error),
+    #[error("The radio playlist doesn't contain a stream")]
+    InvalidPlaylist,
+    #[error("The link isn't a radio stream")]
+    NotAStream,
+    #[error("{0}")]
+    Url(#[from] crate::url_guard::UrlGuardError)
+}
+
+// .pls and .m3u links only point to the actual stream
+pub fn is_playlist_url(url: &Url) -> bool {
+    let extension = url.path_segments()
+        .and_then(|segments| segments.last())
+        .and_then(|file_name| file_name.rsplit_once('.'))
+        .map(|(_, extension)| extension.to_lowercase());
+    extension.is_some_and(|extension| PLAYLIST_EXTENSIONS.contains(&extension.as_str()))
+}
+
+async fn request(client: &Client, url: &str) -> Result<reqwest::Response, RadioError> {
+    crate::url_guard::check_url(url).await?;
+    // servers only interleave metadata into the stream if it's asked for
+    Ok(client.get(url).header("Icy-MetaData", "1").send().await?.error_for_status()?)
+}
+
+// returns the first stream listed in a .pls or .m3u playlist
+pub async fn resolve_playlist(client: &Client, url: &str) -> Result<String, RadioError> {
+    let response = request(client, url).await?;
+    let content = crate::url_guard::read_limited_text(response, MAX_PLAYLIST_SIZE).await?.ok_or(RadioError::InvalidPlaylist)?;
+
+    content.lines().map(str::trim).find_map(|line| {
+        // pls entries look like "File1=http://...", m3u entries are plain lines
+        let location = match line.split_once('=') {
+            Some((key, value)) if key.to_lowercase().starts_with("file") => value.trim(),
+            _ => line
+        };
+        let url = Url::parse(location).ok()?;
+        matches!(url.scheme(), "http" | "https").then(|| location.to_owned())
+    }).ok_or(RadioError::InvalidPlaylist)
+}
+
+// None if the url is an ordinary file, streams either send icy headers or audio without a length
+pub async fn probe_station(client: &Client, url: &str) -> Result<Option<VideoMetadata>, RadioError> {
+    let response = request(client, url).await?;
+    let headers = response.headers();
+
+    let has_icy_headers = headers.keys().any(|key| key.as_str().starts_with("icy-"));
+    let is_unbounded_audio = content_type(headers).is_some_and(|content_type| content_type.starts_with("audio/")) && headers.get(CONTENT_LENGTH).is_none();
+    if !has_icy_headers && !is_unbounded_audio { return Ok(None); }
+
+    let title = headers.get("icy-name")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())
+        .or_else(|| Url::parse(url).ok().and_then(|url| url.host_str().map(|host| host.to_owned())))
+        .unwrap_or_else(|| url.to_owned());
+
+    Ok(Some(VideoMetadata { title, duration: std::time::Duration::ZERO, audio_source: AudioSource::Radio { url: url.to_owned(), stream_title: StreamTitle::default() } }))
+}
+
+fn content_type(headers: &HeaderMap) -> Option<String> {
+    headers.get(CONTENT_TYPE).and_then(|value| value.to_str().ok()).map(|value| value.to_lowercase())
+}
+
+// connects to the stream, the current song gets written to `stream_title` whenever the station announces a new one
+pub async fn create_radio_stream(client: Client, url: String, stream_title: StreamTitle) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+    let response = request(&client, &url).await.map_err(|err| AudioStreamError::Fail(err.into()))?;
+    let headers = response.headers();
+
+    let hint = content_type(headers).map(|content_type| {
+        let mut hint = Hint::new();
+        hint.mime_type(&content_type);
+        hint
+    });
+    let metaint = headers
+        .get("icy-metaint")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .filter(|metaint| *metaint > 0);
+
+    let stream = IcyStream {
+        stream: Box::pin(response.bytes_stream().map_ok(|bytes| bytes.to_vec())),
+        chunk: vec![],
+        chunk_offset: 0,
+        metaint,
+        audio_left: metaint.unwrap_or_default(),
+        metadata: None,
+        stream_title
+    };
+    let input = AsyncAdapterStream::new(Box::new(stream), 64 * 1024);
+    Ok(AudioStream { input: Box::new(input) as Box<dyn MediaSource>, hint })
+}
+
+// http body of a radio stream with the icy metadata blocks cut out of it
+struct IcyStream {
+    stream: ByteStream,
+    chunk: Vec<u8>,
+    chunk_offset: usize,
+    metaint: Option<usize>, // audio bytes between metadata blocks
+    audio_left: usize,
+    metadata: Option<(usize, Vec<u8>)>, // length and content of the block being read
+    stream_title: StreamTitle
+}
+
+impl IcyStream {
+    fn finish_metadata(&mut self, metadata: &[u8]) {
+        if let Some(title) = parse_stream_title(&String::from_utf8_lossy(metadata)) {
+            self.stream_title.set(title);
+        }
+        self.audio_left = self.metaint.unwrap_or_default();
+    }
+}
+
+// metadata blocks look like "StreamTitle='Artist - Song';StreamUrl='';" padded with zeros
+fn parse_stream_title(metadata: &str) -> Option<String> {
+    let start = metadata.find("StreamTitle='")? + "StreamTitle='".len();
+    let rest = &metadata[start..];
+    let end = rest.find("';").unwrap_or(rest.trim_end_matches('\0').len());
+    let title = rest[..end].trim();
+    (!title.is_empty()).then(|| title.to_owned())
+}
+
+impl AsyncRead for IcyStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        if buf.remaining() == 0 { return Poll::Ready(Ok(())); }
+
+        loop {
+            while this.chunk_offset >= this.chunk.len() {
+                match this.stream.poll_next_unpin(cx) {
+                    Poll::Ready(Some(Ok(chunk))) => {
+                        this.chunk = chunk;
+                        this.chunk_offset = 0;
+                    },
+                    Poll::Ready(Some(Err(err))) => return Poll::Ready(Err(IoError::new(IoErrorKind::Other, err))),
+                    Poll::Ready(None) => return Poll::Ready(Ok(())),
+                    Poll::Pending => return Poll::Pending
+                }
+            }
+            let available = &this.chunk[this.chunk_offset..];
+
+            if let Some((len, mut metadata)) = this.metadata.take() {
+                let amount = available.len().min(len - metadata.len());
+                metadata.extend_from_slice(&available[..amount]);
+                this.chunk_offset += amount;
+                match metadata.len() == len {
+                    true => this.finish_metadata(&metadata),
+                    false => this.metadata = Some((len, metadata))
+                }
+                continue;
+            }
+
+            if this.metaint.is_some() && this.audio_left == 0 {
+                // the length byte counts in blocks of 16 bytes, most of the time it's zero
+                let len = available[0] as usize * 16;
+                this.chunk_offset += 1;
+                match len {
+                    0 => this.audio_left = this.metaint.unwrap_or_default(),
+                    len => this.metadata = Some((len, Vec::with_capacity(len)))
+                }
+                continue;
+            }
+
+            let mut amount = buf.remaining().min(available.len());
+            if this.metaint.is_some() {
+                amount = amount.min(this.audio_left);
+                this.audio_left -= amount;
+            }
+            buf.put_slice(&available[..amount]);
+            this.chunk_offset += amount;
+            return Poll::Ready(Ok(()));
+        }
+    }
+}
+
+impl AsyncSeek for IcyStream {
+    fn start_seek(self: Pin<&mut Self>, _position: SeekFrom) -> IoResult<()> {
+        Err(IoErrorKind::Unsupported.into())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<IoResult<u64>> {
+        Poll::Ready(Err(IoErrorKind::Unsupported.into()))
+    }
+}
+
+#[serenity::async_trait]
+impl AsyncMediaSource for IcyStream {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    async fn byte_len(&self) -> Option<u64> {
+        None
+    }
+
+    async fn try_resume(&mut self, _offset: u64) -> Result<Box<dyn AsyncMediaSource>, AudioStreamError> {
+        Err(AudioStreamError::Unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn parses_stream_titles() {
+        assert_eq!(parse_stream_title("StreamTitle='Daft Punk - One More Time';StreamUrl='';\0\0\0"), Some("Daft Punk - One More Time".to_owned()));
+        assert_eq!(parse_stream_title("StreamTitle='It's Raining';"), Some("It's Raining".to_owned()));
+        assert_eq!(parse_stream_title("StreamTitle='No terminator\0\0\0"), Some("No terminator".to_owned()));
+        assert_eq!(parse_stream_title("StreamTitle='';\0\0"), None);
+        assert_eq!(parse_stream_title("StreamUrl='http://example.com';"), None);
+    }
+
+    #[tokio::test]
+    async fn strips_metadata_blocks() {
+        let mut metadata = b"StreamTitle='Artist - Song';".to_vec();
+        metadata.resize(32, 0);
+        let mut body = b"abcd".to_vec();
+        body.push(2);
+        body.extend_from_slice(&metadata);
+        body.extend_from_slice(b"efgh");
+        body.push(0);
+        body.extend_from_slice(b"ij");
+
+        // small chunks so blocks are split across them
+        let chunks = body.chunks(3).map(|chunk| Ok(chunk.to_vec())).collect::<Vec<Result<Vec<u8>, reqwest::Error>>>();
+        let stream_title = StreamTitle::default();
+        let mut stream = IcyStream {
+            stream: Box::pin(futures::stream::iter(chunks)),
+            chunk: vec![],
+            chunk_offset: 0,
+            metaint: Some(4),
+            audio_left: 4,
+            metadata: None,
+            stream_title: stream_title.clone()
+        };
+
+        let mut audio = vec![];
+        stream.read_to_end(&mut audio).await.unwrap();
+        assert_eq!(audio, b"abcdefghij");
+        assert_eq!(stream_title.get(), Some("Artist - Song".to_owned()));
+    }
+}
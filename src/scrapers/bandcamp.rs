@@ -0,0 +1,95 @@
+use std::{collections::HashMap, time::Duration};
+
+use nom::{IResult, bytes::complete::{tag, take_until}, sequence::preceded};
+use reqwest::Client;
+use serde::Deserialize;
+use thiserror::Error as ThisError;
+use url::Url;
+use crate::metadata::{AudioSource, VideoMetadata};
+
+#[derive(Debug, ThisError)]
+pub enum BandcampError {
+    #[error("")]
+    Parse,
+    #[error("")]
+    Request(#[from] reqwest::Error),
+    #[error("")]
+    Json(#[from] serde_json::Error),
+    #[error("")]
+    Url(#[from] url::ParseError),
+    #[error("This Bandcamp track can't be streamed")]
+    NotStreamable
+}
+
+// album and track data embedded into every release page
+#[derive(Debug, Deserialize)]
+struct Tralbum {
+    artist: String,
+    trackinfo: Vec<TrackInfo>
+}
+
+#[derive(Debug, Deserialize)]
+struct TrackInfo {
+    title: String,
+    duration: f64, // s
+    title_link: Option<String>,
+    file: Option<HashMap<String, String>> // missing for tracks which can only be bought
+}
+
+impl TrackInfo {
+    fn stream_url(&self) -> Option<String> {
+        self.file.as_ref()?.get("mp3-128").cloned()
+    }
+}
+
+fn parse_tralbum(input: &str) -> IResult<&str, &str> {
+    preceded(preceded(take_until("data-tralbum=\""), tag("data-tralbum=\"")), take_until("\""))(input)
+}
+
+fn unescape_html(input: &str) -> String {
+    input
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+async fn scrape_tralbum(client: &Client, url: &str) -> Result<Tralbum, BandcampError> {
+    let html = client.get(url).send().await?.error_for_status()?.text().await?;
+    let (_, tralbum) = parse_tralbum(&html).map_err(|_| BandcampError::Parse)?;
+    Ok(serde_json::from_str(&unescape_html(tralbum))?)
+}
+
+fn video_metadata(artist: &str, track_info: &TrackInfo, url: String) -> VideoMetadata {
+    VideoMetadata {
+        title: format!("{} - {}", artist, track_info.title),
+        duration: Duration::from_secs_f64(track_info.duration.max(0.0)),
+        audio_source: AudioSource::Bandcamp { url }
+    }
+}
+
+pub async fn track(client: &Client, url: &str) -> Result<VideoMetadata, BandcampError> {
+    let tralbum = scrape_tralbum(client, url).await?;
+    let track_info = tralbum.trackinfo.first().filter(|track_info| track_info.stream_url().is_some()).ok_or(BandcampError::NotStreamable)?;
+    Ok(video_metadata(&tralbum.artist, track_info, url.to_owned()))
+}
+
+// tracks which can't be streamed are left out, every track is stored with the link to its own page
+pub async fn album(client: &Client, url: &str) -> Result<Vec<VideoMetadata>, BandcampError> {
+    let album_url = Url::parse(url)?;
+    let tralbum = scrape_tralbum(client, url).await?;
+
+    let mut tracks = vec![];
+    for track_info in tralbum.trackinfo.iter().filter(|track_info| track_info.stream_url().is_some()) {
+        let Some(title_link) = &track_info.title_link else { continue; };
+        tracks.push(video_metadata(&tralbum.artist, track_info, album_url.join(title_link)?.to_string()));
+    }
+    Ok(tracks)
+}
+
+// stream urls expire, so they're only scraped right before playing
+pub async fn stream_url(client: &Client, url: &str) -> Result<String, BandcampError> {
+    let tralbum = scrape_tralbum(client, url).await?;
+    tralbum.trackinfo.first().and_then(|track_info| track_info.stream_url()).ok_or(BandcampError::NotStreamable)
+}
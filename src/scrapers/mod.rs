@@ -1,2 +1,5 @@
 pub mod youtube;
-pub mod jeja;
\ No newline at end of file
+pub mod jeja;
+pub mod soundcloud;
+pub mod bandcamp;
+pub mod podcast;
\ No newline at end of file
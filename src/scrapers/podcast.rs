@@ -0,0 +1,204 @@
+use nom::{IResult, bytes::complete::{tag, take_until}, sequence::{delimited, preceded}};
+use reqwest::{Client, header::CONTENT_TYPE};
+use serde::Deserialize;
+use thiserror::Error as ThisError;
+use crate::metadata::{AudioSource, VideoMetadata};
+
+const ITUNES_SEARCH_URL: &str = "https://itunes.apple.com/search";
+const MAX_FEED_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, ThisError)]
+pub enum PodcastError {
+    #[error("")]
+    Request(#[from] reqwest::Error),
+    #[error("")]
+    Json(#[from] serde_json::Error),
+    #[error("The podcast feed is too large")]
+    FeedTooLarge,
+    #[error("The podcast feed has no playable episodes")]
+    NoEpisodes,
+    #[error("Couldn't find this episode in the show's public feed")]
+    EpisodeNotFound,
+    #[error("{0}")]
+    Url(#[from] crate::url_guard::UrlGuardError)
+}
+
+#[derive(Debug, Clone)]
+pub struct Episode {
+    pub title: String,
+    pub published: Option<String>,
+    pub video_metadata: VideoMetadata
+}
+
+#[derive(Debug, Deserialize)]
+struct ITunesResults {
+    results: Vec<ITunesPodcast>
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ITunesPodcast {
+    collection_name: String,
+    feed_url: Option<String>
+}
+
+fn parse_item(input: &str) -> IResult<&str, &str> {
+    preceded(preceded(take_until("<item"), tag("<item")), take_until("</item>"))(input)
+}
+
+fn parse_element<'a>(input: &'a str, name: &str) -> Option<&'a str> {
+    let open = format!("<{}", name);
+    let close = format!("</{}>", name);
+    let result: IResult<&str, &str> = preceded(
+        preceded(take_until(open.as_str()), tag(open.as_str())),
+        preceded(preceded(take_until(">"), tag(">")), take_until(close.as_str()))
+    )(input);
+    result.ok().map(|(_, content)| content)
+}
+
+fn parse_enclosure_url(input: &str) -> Option<&str> {
+    let result: IResult<&str, &str> = preceded(
+        take_until("<enclosure"),
+        preceded(take_until("url=\""), delimited(tag("url=\""), take_until("\""), tag("\"")))
+    )(input);
+    result.ok().map(|(_, url)| url)
+}
+
+// element text can be wrapped in cdata or contain escaped entities
+fn element_text(content: &str) -> String {
+    let content = content.trim();
+    match content.strip_prefix("<![CDATA[").and_then(|content| content.strip_suffix("]]>")) {
+        Some(content) => content.trim().to_owned(),
+        None => unescape_xml(content)
+    }
+}
+
+fn unescape_xml(input: &str) -> String {
+    input
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn parse_episodes(feed: &str) -> Vec<Episode> {
+    let mut episodes = vec![];
+    let mut rest = feed;
+    while let Ok((next, item)) = parse_item(rest) {
+        rest = next;
+        // episodes without audio (e.g. announcements) can't be played
+        let (Some(title), Some(url)) = (parse_element(item, "title"), parse_enclosure_url(item)) else { continue; };
+
+        let title = element_text(title);
+        let duration = parse_element(item, "itunes:duration")
+            .and_then(|duration| crate::utils::parse_timestamp(&element_text(duration)))
+            .unwrap_or_default();
+        let published = parse_element(item, "pubDate").map(element_text);
+        let video_metadata = VideoMetadata { title: title.clone(), duration, audio_source: AudioSource::Url { url: unescape_xml(url) } };
+        episodes.push(Episode { title, published, video_metadata });
+    }
+    episodes
+}
+
+// None if the url doesn't point to an rss feed, feeds list their newest episodes first
+pub async fn feed(client: &Client, url: &str) -> Result<Option<Vec<Episode>>, PodcastError> {
+    crate::url_guard::check_url(url).await?;
+    let response = client.get(url).send().await?.error_for_status()?;
+    let is_xml = response.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("xml"));
+    if !is_xml { return Ok(None); }
+    let feed = crate::url_guard::read_limited_text(response, MAX_FEED_SIZE).await?.ok_or(PodcastError::FeedTooLarge)?;
+    if !feed.contains("<rss") { return Ok(None); }
+
+    let episodes = parse_episodes(&feed);
+    if episodes.is_empty() { return Err(PodcastError::NoEpisodes); }
+    Ok(Some(episodes))
+}
+
+fn normalize_title(title: &str) -> String {
+    title.to_lowercase().chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+// spotify doesn't expose feeds, so the show is looked up in the itunes directory which does
+pub async fn find_episode(client: &Client, show: &str, title: &str) -> Result<VideoMetadata, PodcastError> {
+    let json = client.get(ITUNES_SEARCH_URL)
+        .query(&[("media", "podcast"), ("entity", "podcast"), ("term", show)])
+        .send().await?
+        .error_for_status()?
+        .text().await?;
+    let results = serde_json::from_str::<ITunesResults>(&json)?.results;
+
+    let show = normalize_title(show);
+    let feed_url = results.iter()
+        .find(|podcast| normalize_title(&podcast.collection_name) == show)
+        .and_then(|podcast| podcast.feed_url.clone())
+        .ok_or(PodcastError::EpisodeNotFound)?;
+
+    let episodes = feed(client, &feed_url).await?.ok_or(PodcastError::EpisodeNotFound)?;
+    let title = normalize_title(title);
+    episodes.into_iter()
+        .find(|episode| normalize_title(&episode.title) == title)
+        .map(|episode| episode.video_metadata)
+        .ok_or(PodcastError::EpisodeNotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+  <channel>
+    <title>The Show</title>
+    <item>
+      <title><![CDATA[ Episode 2: Q&A ]]></title>
+      <pubDate>Tue, 03 Sep 2024 04:00:00 GMT</pubDate>
+      <itunes:duration>1:02:03</itunes:duration>
+      <enclosure url="https://cdn.example.com/ep2.mp3?a=1&amp;b=2" length="123" type="audio/mpeg"/>
+    </item>
+    <item>
+      <title>Schedule change</title>
+      <pubDate>Mon, 02 Sep 2024 04:00:00 GMT</pubDate>
+    </item>
+    <item>
+      <title>Episode 1: Tom &amp; Jerry&#39;s &quot;pilot&quot;</title>
+      <itunes:duration>2712</itunes:duration>
+      <enclosure length="456" type="audio/mpeg" url="https://cdn.example.com/ep1.mp3"/>
+    </item>
+  </channel>
+</rss>"#;
+
+    #[test]
+    fn parses_episodes() {
+        let episodes = parse_episodes(FEED);
+        assert_eq!(episodes.len(), 2);
+
+        assert_eq!(episodes[0].title, "Episode 2: Q&A");
+        assert_eq!(episodes[0].published.as_deref(), Some("Tue, 03 Sep 2024 04:00:00 GMT"));
+        assert_eq!(episodes[0].video_metadata.duration.as_secs(), 3723);
+        assert!(matches!(&episodes[0].video_metadata.audio_source, AudioSource::Url { url } if url == "https://cdn.example.com/ep2.mp3?a=1&b=2"));
+
+        assert_eq!(episodes[1].title, "Episode 1: Tom & Jerry's \"pilot\"");
+        assert_eq!(episodes[1].published, None);
+        assert_eq!(episodes[1].video_metadata.duration.as_secs(), 2712);
+        assert!(matches!(&episodes[1].video_metadata.audio_source, AudioSource::Url { url } if url == "https://cdn.example.com/ep1.mp3"));
+    }
+
+    #[test]
+    fn reads_element_text() {
+        assert_eq!(element_text("  <![CDATA[ <b>bold</b> &amp; ]]>  "), "<b>bold</b> &amp;");
+        assert_eq!(element_text("Fish &amp; Chips &lt;live&gt;"), "Fish & Chips <live>");
+        // decoded once, so escaped entities stay escaped
+        assert_eq!(element_text("&amp;lt;"), "&lt;");
+    }
+
+    #[test]
+    fn empty_feeds_have_no_episodes() {
+        assert!(parse_episodes("<rss><channel><title>Nothing</title></channel></rss>").is_empty());
+        assert!(parse_episodes("").is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+use std::time::Duration;
+
+use nom::{IResult, bytes::complete::{tag, take_until}, sequence::preceded};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use thiserror::Error as ThisError;
+use tokio::sync::Mutex;
+use crate::metadata::{AudioSource, VideoMetadata};
+
+const API_URL: &str = "https://api-v2.soundcloud.com";
+const TRACKS_PER_REQUEST: usize = 50;
+
+// the public web client id, scraped once and reused until the api rejects it
+static CLIENT_ID: Mutex<Option<String>> = Mutex::const_new(None);
+
+#[derive(Debug, ThisError)]
+pub enum SoundCloudError {
+    #[error("")]
+    ClientId,
+    #[error("")]
+    Request(#[from] reqwest::Error),
+    #[error("")]
+    Json(#[from] serde_json::Error),
+    #[error("This SoundCloud link isn't a track or a set")]
+    UnsupportedKind,
+    #[error("This SoundCloud track can't be streamed")]
+    NotStreamable
+}
+
+#[derive(Debug, Deserialize)]
+struct Resolved {
+    kind: String,
+    #[serde(default)]
+    tracks: Vec<TrackStub>
+}
+
+// tracks in sets only come with their id past the first few
+#[derive(Debug, Deserialize)]
+struct TrackStub {
+    id: u64
+}
+
+#[derive(Debug, Deserialize)]
+struct Track {
+    id: u64,
+    title: String,
+    duration: u64, // ms
+    permalink_url: String,
+    #[serde(default)]
+    streamable: Option<bool>,
+    track_authorization: Option<String>,
+    media: Option<Media>
+}
+
+#[derive(Debug, Deserialize)]
+struct Media {
+    transcodings: Vec<Transcoding>
+}
+
+#[derive(Debug, Deserialize)]
+struct Transcoding {
+    url: String,
+    format: TranscodingFormat
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscodingFormat {
+    protocol: String
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamLocation {
+    url: String
+}
+
+impl From<Track> for VideoMetadata {
+    fn from(track: Track) -> Self {
+        VideoMetadata {
+            title: track.title,
+            duration: Duration::from_millis(track.duration),
+            audio_source: AudioSource::SoundCloud { track_id: track.id, url: track.permalink_url }
+        }
+    }
+}
+
+fn parse_script_urls(input: &str) -> Vec<String> {
+    let mut script_urls = vec![];
+    let mut rest = input;
+    while let Ok((next, script_url)) = parse_script_url(rest) {
+        script_urls.push(script_url.to_owned());
+        rest = next;
+    }
+    script_urls
+}
+
+fn parse_script_url(input: &str) -> IResult<&str, &str> {
+    preceded(preceded(take_until("<script crossorigin src=\""), tag("<script crossorigin src=\"")), take_until("\""))(input)
+}
+
+fn parse_client_id(input: &str) -> IResult<&str, &str> {
+    preceded(preceded(take_until("client_id:\""), tag("client_id:\"")), take_until("\""))(input)
+}
+
+async fn scrape_client_id(client: &Client) -> Result<String, SoundCloudError> {
+    if let Ok(client_id) = std::env::var("SOUNDCLOUD_CLIENT_ID") { return Ok(client_id); }
+
+    let html = client.get("https://soundcloud.com").send().await?.text().await?;
+    // the id is defined in one of the last bundles
+    for script_url in parse_script_urls(&html).into_iter().rev() {
+        let script = client.get(script_url).send().await?.text().await?;
+        if let Ok((_, client_id)) = parse_client_id(&script) {
+            return Ok(client_id.to_owned());
+        }
+    }
+    Err(SoundCloudError::ClientId)
+}
+
+// requests an api url, the client id is scraped again once if the cached one expired
+async fn api_get(client: &Client, url: &str, query: &[(&str, &str)]) -> Result<String, SoundCloudError> {
+    for _ in 0..2 {
+        let client_id = {
+            let mut cached_client_id = CLIENT_ID.lock().await;
+            match cached_client_id.as_ref() {
+                Some(client_id) => client_id.clone(),
+                None => cached_client_id.insert(scrape_client_id(client).await?).clone()
+            }
+        };
+
+        let response = client.get(url).query(query).query(&[("client_id", client_id.as_str())]).send().await?;
+        if matches!(response.status(), StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+            *CLIENT_ID.lock().await = None;
+            continue;
+        }
+        return Ok(response.error_for_status()?.text().await?);
+    }
+    Err(SoundCloudError::ClientId)
+}
+
+async fn track(client: &Client, track_id: u64) -> Result<Track, SoundCloudError> {
+    let json = api_get(client, &format!("{}/tracks/{}", API_URL, track_id), &[]).await?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+pub async fn resolve_track(client: &Client, url: &str) -> Result<VideoMetadata, SoundCloudError> {
+    let json = api_get(client, &format!("{}/resolve", API_URL), &[("url", url)]).await?;
+    let resolved = serde_json::from_str::<Resolved>(&json)?;
+    if resolved.kind != "track" { return Err(SoundCloudError::UnsupportedKind); }
+    Ok(serde_json::from_str::<Track>(&json)?.into())
+}
+
+// tracks which can't be streamed are left out
+pub async fn resolve_set(client: &Client, url: &str) -> Result<Vec<VideoMetadata>, SoundCloudError> {
+    let json = api_get(client, &format!("{}/resolve", API_URL), &[("url", url)]).await?;
+    let resolved = serde_json::from_str::<Resolved>(&json)?;
+    if resolved.kind != "playlist" { return Err(SoundCloudError::UnsupportedKind); }
+
+    let track_ids = resolved.tracks.iter().map(|track| track.id).collect::<Vec<u64>>();
+    let mut video_metadata = vec![];
+    for ids in track_ids.chunks(TRACKS_PER_REQUEST) {
+        let ids = ids.iter().map(|id| id.to_string()).collect::<Vec<String>>().join(",");
+        let json = api_get(client, &format!("{}/tracks", API_URL), &[("ids", &ids)]).await?;
+        let mut tracks = serde_json::from_str::<Vec<Track>>(&json)?;
+
+        // the tracks endpoint doesn't keep the order of the ids
+        tracks.sort_by_key(|track| track_ids.iter().position(|id| *id == track.id));
+        video_metadata.extend(tracks.into_iter().filter(|track| track.streamable != Some(false)).map(VideoMetadata::from));
+    }
+    Ok(video_metadata)
+}
+
+// stream urls expire, so they're only requested right before playing
+pub async fn stream_url(client: &Client, track_id: u64) -> Result<String, SoundCloudError> {
+    let track = track(client, track_id).await?;
+    let transcoding = track.media
+        .and_then(|media| media.transcodings.into_iter().find(|transcoding| transcoding.format.protocol == "progressive"))
+        .ok_or(SoundCloudError::NotStreamable)?;
+
+    let mut query = vec![];
+    if let Some(track_authorization) = &track.track_authorization {
+        query.push(("track_authorization", track_authorization.as_str()));
+    }
+    let json = api_get(client, &transcoding.url, &query).await?;
+    Ok(serde_json::from_str::<StreamLocation>(&json)?.url)
+}
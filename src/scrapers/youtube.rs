@@ -35,6 +35,14 @@ fn parse_duration_string(input: &str) -> IResult<&str, &str> {
     preceded(take_until("\"lengthText\":"), preceded(take_until("\"simpleText\":\""), preceded(tag("\"simpleText\":\""), take_until("\""))))(input)
 }
 
+fn parse_channel(input: &str) -> IResult<&str, &str> {
+    preceded(preceded(take_until("\"ownerText\":{\"runs\":[{\"text\":\""), tag("\"ownerText\":{\"runs\":[{\"text\":\"")), take_until("\""))(input)
+}
+
+fn decode_json_string(input: &str) -> String {
+    serde_json::from_str(&format!("\"{input}\"")).unwrap_or(input.to_owned())
+}
+
 fn string_to_duration(input: &str) -> Result<std::time::Duration, YoutubeScrapeError> {
     let time_sections = input.split(":").collect::<Vec<&str>>();
     let mut seconds: u64 = 0;
@@ -48,19 +56,36 @@ fn string_to_duration(input: &str) -> Result<std::time::Duration, YoutubeScrapeE
 }
 
 pub async fn search(query: &str) -> Result<crate::metadata::VideoMetadata, YoutubeScrapeError> {
+    let result = search_results(query, 1).await?.into_iter().next().ok_or(YoutubeScrapeError::VideoId)?;
+    Ok(result.video_metadata)
+}
+
+// a single video out of the search results
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub video_metadata: crate::metadata::VideoMetadata,
+    pub channel: String
+}
+
+// returns up to `limit` videos in the order youtube ranked them, live streams are left out since they have no length
+pub async fn search_results(query: &str, limit: usize) -> Result<Vec<SearchResult>, YoutubeScrapeError> {
     let client = Client::new();
     let url = Url::parse("https://www.youtube.com/results")?;
     let request = client.request(Method::GET, url).query(&[("search_query", query)]).build()?;
 
-    let mut doc = client.execute(request).await?.text().await?;
-    let (rest, video_id) = parse_video_id(&doc).map_err(|_| YoutubeScrapeError::VideoId)?;
-    doc = rest.to_owned();
-    let (rest, title) = parse_title(&doc).map_err(|_| YoutubeScrapeError::Title)?;
-    let title = serde_json::from_str(&format!("\"{title}\"")).unwrap_or("ERROR".to_string());
-    doc = rest.to_owned();
-    let (_, duration_string) = parse_duration_string(&doc).map_err(|_| YoutubeScrapeError::DurationPage)?;
-    let duration = string_to_duration(duration_string)?;
-    let audio_source = AudioSource::YouTube { video_id };
+    let doc = client.execute(request).await?.text().await?;
+    let mut results = vec![];
+    // every video in the results is described by its own renderer object
+    for renderer in doc.split("\"videoRenderer\":{").skip(1) {
+        if results.len() == limit { break; }
+        let Ok((rest, video_id)) = parse_video_id(renderer) else { continue; };
+        let Ok((_, title)) = parse_title(rest) else { continue; };
+        let Ok((_, duration_string)) = parse_duration_string(rest) else { continue; };
+        let Ok(duration) = string_to_duration(duration_string) else { continue; };
+        let channel = parse_channel(rest).map(|(_, channel)| decode_json_string(channel)).unwrap_or_default();
 
-    Ok(crate::metadata::VideoMetadata { title, duration, audio_source })
-}
\ No newline at end of file
+        let video_metadata = crate::metadata::VideoMetadata { title: decode_json_string(&title), duration, audio_source: AudioSource::YouTube { video_id } };
+        results.push(SearchResult { video_metadata, channel });
+    }
+    Ok(results)
+}
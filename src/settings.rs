@@ -0,0 +1,65 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::{audio_processing::SharedAudioSettings, permissions::{default_policy, PermissionLevel}, storage::{read_json, write_json, StorageError}};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PREFIX: &str = "-";
+pub const DEFAULT_SETTINGS_PATH: &str = "settings.json";
+
+// guild configuration which survives restarts, fields missing from the file fall back to their defaults
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuildSettings {
+    pub prefix: String,
+    pub afk_timeout_secs: u64,
+    pub announcement_channel: Option<u64>,
+    pub reply_cleanup_secs: u64,
+    pub announcement_cleanup_secs: u64,
+    pub volume: f32,
+    pub vote_skip_ratio: f32,
+    pub dj_role: Option<u64>,
+    pub permissions: HashMap<String, PermissionLevel>,
+    pub audio_settings: SharedAudioSettings
+}
+
+impl Default for GuildSettings {
+    fn default() -> Self {
+        Self {
+            prefix: DEFAULT_PREFIX.to_owned(),
+            afk_timeout_secs: 120,
+            announcement_channel: None,
+            reply_cleanup_secs: 5,
+            announcement_cleanup_secs: 10,
+            volume: 1.0,
+            vote_skip_ratio: 0.5,
+            dj_role: None,
+            permissions: default_policy(),
+            audio_settings: SharedAudioSettings::default()
+        }
+    }
+}
+
+impl GuildSettings {
+    // how long the bot stays in an empty voice channel
+    pub fn afk_timeout(&self) -> Duration {
+        Duration::from_secs(self.afk_timeout_secs)
+    }
+
+    // how long short command replies stay before getting deleted
+    pub fn reply_cleanup_delay(&self) -> Duration {
+        Duration::from_secs(self.reply_cleanup_secs)
+    }
+
+    // how long "now playing" and "added track" messages stay before getting deleted
+    pub fn announcement_cleanup_delay(&self) -> Duration {
+        Duration::from_secs(self.announcement_cleanup_secs)
+    }
+}
+
+pub async fn load_settings(path: &std::path::Path) -> Result<HashMap<u64, GuildSettings>, StorageError> {
+    Ok(read_json(path).await?.unwrap_or_default())
+}
+
+pub async fn save_settings(path: &std::path::Path, settings: &HashMap<u64, &GuildSettings>) -> Result<(), StorageError> {
+    write_json(path, settings).await
+}
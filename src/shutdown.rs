@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use crate::{data::Cleanup, guild_state::GuildStates, queue_persistence::{persist_queue, QueueStore}};
+use serenity::{all::{ChannelId, Http, ShardManager}, builder::{CreateEmbed, CreateMessage}, model::Color};
+use songbird::Songbird;
+use tokio::sync::Mutex;
+
+// everything that has to be wrapped up before the process exits
+pub struct Shutdown {
+    pub http: Arc<Http>,
+    pub songbird: Arc<Songbird>,
+    pub shard_manager: Arc<ShardManager>,
+    pub guild_states: GuildStates,
+    pub queue_store: QueueStore,
+    pub cleanups: Arc<Mutex<Vec<Cleanup>>>
+}
+
+impl Shutdown {
+    pub async fn on_signal(self) {
+        wait_for_signal().await;
+        log::info!("Shutting down");
+        let Self { http, songbird: manager, shard_manager, guild_states, queue_store, cleanups } = self;
+
+        let calls = manager.iter().collect::<Vec<_>>();
+        for (guild_id, handler) in &calls {
+            let guild_id = guild_id.0.get();
+            persist_queue(&queue_store, Some(handler.clone()), &guild_states, guild_id).await;
+
+            let Some(text_channel) = guild_states.with(guild_id, |state| state.text_channel).await else { continue; };
+            let _ = ChannelId::new(text_channel).send_message(&http, CreateMessage::new()
+                .embed(CreateEmbed::new()
+                    .description("The bot is shutting down, the queue has been saved")
+                    .color(Color::PURPLE))
+            ).await;
+        }
+
+        // tracks ending because of the disconnect would otherwise overwrite the saved queues
+        queue_store.freeze();
+        for (guild_id, _) in calls {
+            let _ = manager.remove(guild_id).await;
+        }
+
+        let cleanups = std::mem::take(&mut *cleanups.lock().await);
+        for cleanup in cleanups {
+            let _ = cleanup.message.delete(&http).await;
+        }
+
+        shard_manager.shutdown_all().await;
+    }
+}
+
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => (),
+                    _ = sigterm.recv() => ()
+                }
+            },
+            Err(_) => { let _ = tokio::signal::ctrl_c().await; }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error as ThisError;
+
+#[derive(Debug, ThisError)]
+pub enum StorageError {
+    #[error("Couldn't access a storage file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Couldn't parse a storage file: {0}")]
+    Parse(#[from] serde_json::Error)
+}
+
+// None if the file doesn't exist yet
+pub async fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, StorageError> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into())
+    }
+}
+
+// writes to a temporary file first so a crash mid write doesn't corrupt the file
+pub async fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), StorageError> {
+    let json = serde_json::to_string_pretty(value)?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let temporary_path = path.with_extension("tmp");
+    tokio::fs::write(&temporary_path, json).await?;
+    tokio::fs::rename(&temporary_path, path).await?;
+    Ok(())
+}
+
+pub async fn remove_json(path: &Path) -> Result<(), StorageError> {
+    match tokio::fs::remove_file(path).await {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{api_integration::spotify::SpotifyTrackData, metadata::VideoMetadata, scrapers::youtube::{search, search_results, SearchResult, YoutubeScrapeError}};
+
+const MAX_CANDIDATES: usize = 8;
+// words which mark a different version of a song, unless the song itself is that version
+const VERSION_MARKERS: [&str; 12] = ["live", "cover", "remix", "karaoke", "instrumental", "acoustic", "nightcore", "sped up", "slowed", "8d", "1 hour", "loop"];
+
+// resolves a pending query, spotify tracks get matched against their data instead of taking the first result
+pub async fn resolve_query(query: &str, spotify_track: Option<&SpotifyTrackData>) -> Result<VideoMetadata, YoutubeScrapeError> {
+    match spotify_track {
+        Some(track_data) => find_spotify_track(track_data).await,
+        None => search(query).await
+    }
+}
+
+// picks the search result which is most likely the same recording as the spotify track
+pub async fn find_spotify_track(track_data: &SpotifyTrackData) -> Result<VideoMetadata, YoutubeScrapeError> {
+    let candidates = search_results(&track_data.query(), MAX_CANDIDATES).await?;
+
+    // the same search would come back empty again, so there's nothing to fall back to
+    candidates.into_iter()
+        .enumerate()
+        .map(|(rank, candidate)| (score(track_data, &candidate) - rank as f64, candidate)) // ties go to youtube's ranking
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, candidate)| candidate.video_metadata)
+        .ok_or(YoutubeScrapeError::VideoId)
+}
+
+// lowercase words with punctuation stripped, padded with spaces so phrases only match whole words
+fn normalize(text: &str) -> String {
+    let words = text.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>();
+    format!(" {} ", words.split_whitespace().collect::<Vec<&str>>().join(" "))
+}
+
+fn contains_phrase(normalized: &str, phrase: &str) -> bool {
+    let phrase = normalize(phrase);
+    !phrase.trim().is_empty() && normalized.contains(&phrase)
+}
+
+fn score(track_data: &SpotifyTrackData, candidate: &SearchResult) -> f64 {
+    let original_title = normalize(&track_data.title);
+    let title = normalize(&candidate.video_metadata.title);
+    let channel = normalize(&candidate.channel);
+    let mut score = 0.0;
+
+    // uploads whose length is off by more than a few seconds are a different edit of the song, or something else entirely
+    if !track_data.duration.is_zero() {
+        let difference = track_data.duration.as_secs_f64() - candidate.video_metadata.duration.as_secs_f64();
+        score += match difference.abs() {
+            difference if difference <= 3.0 => 30.0,
+            difference if difference <= 10.0 => 15.0,
+            difference if difference <= 30.0 => 0.0,
+            difference => -30.0 - (difference / 60.0).min(30.0)
+        };
+    }
+
+    for (index, artist) in track_data.artists.iter().enumerate() {
+        let weight = if index == 0 { 15.0 } else { 5.0 };
+        if contains_phrase(&title, artist) || contains_phrase(&channel, artist) {
+            score += weight;
+        }
+    }
+
+    // auto generated "Artist - Topic" channels only upload the studio recordings
+    if channel.ends_with(" topic ") { score += 20.0; }
+    if contains_phrase(&title, "official audio") { score += 10.0; }
+    else if contains_phrase(&title, "official video") || contains_phrase(&title, "official music video") { score += 5.0; }
+
+    for marker in VERSION_MARKERS {
+        if contains_phrase(&title, marker) && !contains_phrase(&original_title, marker) {
+            score -= 25.0;
+        }
+    }
+
+    let title_words = original_title.split_whitespace().collect::<Vec<&str>>();
+    if !title_words.is_empty() {
+        let found = title_words.iter().filter(|word| title.contains(&format!(" {} ", word))).count();
+        score += 20.0 * found as f64 / title_words.len() as f64;
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::metadata::AudioSource;
+    use super::*;
+
+    fn track() -> SpotifyTrackData {
+        SpotifyTrackData { title: "One More Time".to_owned(), artists: vec!["Daft Punk".to_owned()], duration: Duration::from_secs(320) }
+    }
+
+    fn candidate(title: &str, channel: &str, duration: u64) -> SearchResult {
+        SearchResult {
+            video_metadata: VideoMetadata { title: title.to_owned(), duration: Duration::from_secs(duration), audio_source: AudioSource::YouTube { video_id: "dQw4w9WgXcQ".to_owned() } },
+            channel: channel.to_owned()
+        }
+    }
+
+    #[test]
+    fn prefers_the_studio_recording() {
+        let track = track();
+        let topic = score(&track, &candidate("One More Time", "Daft Punk - Topic", 321));
+        let official = score(&track, &candidate("Daft Punk - One More Time (Official Video)", "Daft Punk", 322));
+        let live = score(&track, &candidate("Daft Punk - One More Time (Live)", "Daft Punk", 340));
+        let cover = score(&track, &candidate("One More Time - Daft Punk cover", "Someone", 318));
+        let unrelated = score(&track, &candidate("Around the World", "Daft Punk", 429));
+        let long_version = score(&track, &candidate("Daft Punk - One More Time 1 hour", "Loops", 3600));
+
+        assert!(topic > official, "{} {}", topic, official);
+        assert!(official > live, "{} {}", official, live);
+        assert!(official > cover, "{} {}", official, cover);
+        assert!(live > unrelated, "{} {}", live, unrelated);
+        assert!(cover > long_version, "{} {}", cover, long_version);
+    }
+
+    #[test]
+    fn versions_named_in_the_track_arent_penalized() {
+        let track = SpotifyTrackData { title: "One More Time - Live".to_owned(), ..track() };
+        let live = score(&track, &candidate("Daft Punk - One More Time (Live)", "Daft Punk", 320));
+        let studio = score(&track, &candidate("Daft Punk - One More Time", "Daft Punk", 320));
+        assert!(live > studio, "{} {}", live, studio);
+    }
+
+    #[test]
+    fn markers_only_match_whole_words() {
+        // "live" inside "alive" isn't a live version
+        let track = SpotifyTrackData { title: "Alive".to_owned(), ..track() };
+        let original = score(&track, &candidate("Daft Punk - Alive", "Daft Punk", 320));
+        let live = score(&track, &candidate("Daft Punk - Alive (Live)", "Daft Punk", 320));
+        assert!(original > live, "{} {}", original, live);
+    }
+}
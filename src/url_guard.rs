@@ -0,0 +1,135 @@
+use std::{net::{IpAddr, SocketAddr}, sync::Arc};
+
+use reqwest::{dns::{Addrs, Name, Resolve, Resolving}, redirect::{Attempt, Policy}};
+use thiserror::Error as ThisError;
+use url::{Host, Url};
+
+const MAX_REDIRECTS: usize = 10;
+
+#[derive(Debug, ThisError)]
+pub enum UrlGuardError {
+    #[error("Invalid link")]
+    Invalid,
+    #[error("Couldn't resolve the link")]
+    Resolve,
+    #[error("This link points to an address which can't be played")]
+    Forbidden
+}
+
+// links posted by users mustn't reach the host itself or the network it's in
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            let is_shared = first == 100 && (second & 0xc0) == 64; // 100.64.0.0/10
+            !(ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast() || ip.is_documentation() || first == 0 || is_shared)
+        },
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => {
+                let first_segment = ip.segments()[0];
+                let is_unique_local = (first_segment & 0xfe00) == 0xfc00;
+                let is_link_local = (first_segment & 0xffc0) == 0xfe80;
+                !(ip.is_loopback() || ip.is_unspecified() || is_unique_local || is_link_local)
+            }
+        }
+    }
+}
+
+// resolves the host of a link and rejects it if any of its addresses isn't public, called before anything gets requested
+pub async fn check_url(url: &str) -> Result<(), UrlGuardError> {
+    let url = Url::parse(url).map_err(|_| UrlGuardError::Invalid)?;
+    if !matches!(url.scheme(), "http" | "https") { return Err(UrlGuardError::Invalid); }
+    let port = url.port_or_known_default().ok_or(UrlGuardError::Invalid)?;
+
+    let addresses = match url.host().ok_or(UrlGuardError::Invalid)? {
+        Host::Ipv4(ip) => vec![IpAddr::V4(ip)],
+        Host::Ipv6(ip) => vec![IpAddr::V6(ip)],
+        Host::Domain(domain) => tokio::net::lookup_host((domain, port)).await
+            .map_err(|_| UrlGuardError::Resolve)?
+            .map(|address| address.ip())
+            .collect()
+    };
+    if addresses.is_empty() { return Err(UrlGuardError::Resolve); }
+    match addresses.into_iter().all(is_public_ip) {
+        true => Ok(()),
+        false => Err(UrlGuardError::Forbidden)
+    }
+}
+
+// http client for requests to user provided links, redirects and every dns lookup are checked again
+pub fn guarded_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .dns_resolver(Arc::new(PublicResolver))
+        .redirect(Policy::custom(check_redirect))
+        .build()
+        .unwrap_or_default()
+}
+
+// reads a response body up to `limit` bytes, None if it's longer, the length header can be missing or wrong
+pub async fn read_limited_text(mut response: reqwest::Response, limit: usize) -> Result<Option<String>, reqwest::Error> {
+    if response.content_length().is_some_and(|len| len as usize > limit) { return Ok(None); }
+    let mut body = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > limit { return Ok(None); }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+// hostnames are checked by the resolver, so only addresses written into the link are left to check here
+fn check_redirect(attempt: Attempt) -> reqwest::redirect::Action {
+    if attempt.previous().len() >= MAX_REDIRECTS { return attempt.error(UrlGuardError::Invalid); }
+    let is_forbidden = match attempt.url().host() {
+        Some(Host::Ipv4(ip)) => !is_public_ip(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => !is_public_ip(IpAddr::V6(ip)),
+        Some(Host::Domain(_)) => false,
+        None => true
+    };
+    match is_forbidden {
+        true => attempt.error(UrlGuardError::Forbidden),
+        false => attempt.follow()
+    }
+}
+
+// leaves out addresses which aren't public, so a hostname can't be pointed at the host after it was checked
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses = tokio::net::lookup_host((name.as_str(), 0)).await?
+                .filter(|address| is_public_ip(address.ip()))
+                .collect::<Vec<SocketAddr>>();
+            if addresses.is_empty() { return Err(Box::new(UrlGuardError::Forbidden) as Box<dyn std::error::Error + Send + Sync>); }
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_internal_addresses() {
+        for ip in ["127.0.0.1", "10.0.0.5", "172.16.3.4", "192.168.1.1", "169.254.169.254", "0.0.0.0", "100.64.0.1", "::1", "::", "fc00::1", "fe80::1", "::ffff:127.0.0.1"] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn accepts_public_addresses() {
+        for ip in ["1.1.1.1", "142.250.74.14", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn checks_links_before_requesting() {
+        assert!(matches!(check_url("http://127.0.0.1:8000/stream.mp3").await, Err(UrlGuardError::Forbidden)));
+        assert!(matches!(check_url("http://[::1]/feed.xml").await, Err(UrlGuardError::Forbidden)));
+        assert!(matches!(check_url("http://169.254.169.254/latest/meta-data/").await, Err(UrlGuardError::Forbidden)));
+        assert!(matches!(check_url("file:///etc/passwd").await, Err(UrlGuardError::Invalid)));
+    }
+}
@@ -1,6 +1,7 @@
-use serenity::{builder::{CreateEmbed, CreateEmbedAuthor}, model::Color};
+use poise::ChoiceParameter;
+use serenity::{builder::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter}, model::Color};
 
-use crate::{metadata::AudioSource, metadata::TrackMetadata};
+use crate::{audio_processing::FilterPreset, metadata::AudioSource, metadata::TrackMetadata};
 
 pub fn format_duration(duration: std::time::Duration, length: Option<usize>) -> String {
     let s = duration.as_secs() % 60;
@@ -27,10 +28,46 @@ pub fn format_duration(duration: std::time::Duration, length: Option<usize>) ->
     formatted_duration
 }
 
-pub fn create_now_playing_embed(track_metadata: TrackMetadata) -> CreateEmbed {
+// parses timestamps like "83", "1:23", "1:02:03" or "1h2m3s"
+pub fn parse_timestamp(timestamp: &str) -> Option<std::time::Duration> {
+    let timestamp = timestamp.trim();
+    if timestamp.is_empty() { return None; }
+
+    let mut seconds: u64 = 0;
+    if timestamp.contains(':') {
+        let time_sections = timestamp.split(':').collect::<Vec<&str>>();
+        if time_sections.len() > 3 { return None; }
+        for time_section in time_sections {
+            seconds = seconds.checked_mul(60)?.checked_add(time_section.parse::<u64>().ok()?)?;
+        }
+    } else if timestamp.chars().all(|c| c.is_ascii_digit()) {
+        seconds = timestamp.parse().ok()?;
+    } else {
+        let mut number = String::new();
+        for c in timestamp.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let multiplier = match c {
+                'h' => 3600,
+                'm' => 60,
+                's' => 1,
+                _ => return None
+            };
+            seconds = seconds.checked_add(number.parse::<u64>().ok()?.checked_mul(multiplier)?)?;
+            number.clear();
+        }
+        if !number.is_empty() { return None; }
+    }
+
+    Some(std::time::Duration::from_secs(seconds))
+}
+
+pub fn create_now_playing_embed(track_metadata: TrackMetadata, filter: FilterPreset) -> CreateEmbed {
     let added_by = track_metadata.added_by;
     let video_metadata = track_metadata.video_metadata;
-    let formatted_duration = format_duration(video_metadata.duration, None);
+    let formatted_duration = video_metadata.formatted_duration();
     let mut embed = CreateEmbed::default()
         .title("Now Playing:")
         .color(Color::FADED_PURPLE);
@@ -45,11 +82,21 @@ pub fn create_now_playing_embed(track_metadata: TrackMetadata) -> CreateEmbed {
         AudioSource::File { .. } => {
             embed = embed.description(format!("{} | {}", video_metadata.title, formatted_duration));
         }
+        AudioSource::Url { url } | AudioSource::SoundCloud { url, .. } | AudioSource::Bandcamp { url } => {
+            embed = embed.description(format!("[{}]({}) | {}", video_metadata.title, url, formatted_duration));
+        }
+        AudioSource::Radio { ref url, .. } => {
+            embed = embed.description(format!("[{}]({}) | {}", video_metadata.display_title(), url, formatted_duration));
+        }
         AudioSource::Jeja { .. } => {
             embed = embed.description(video_metadata.title.clone());
         }
     }
 
+    if filter != FilterPreset::Off {
+        embed = embed.footer(CreateEmbedFooter::new(format!("filter: {}", filter.name())));
+    }
+
     embed
         .author({
             let mut author = CreateEmbedAuthor::new(added_by.name)
@@ -60,3 +107,26 @@ pub fn create_now_playing_embed(track_metadata: TrackMetadata) -> CreateEmbed {
             author
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::*;
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("83"), Some(Duration::from_secs(83)));
+        assert_eq!(parse_timestamp(" 1:23 "), Some(Duration::from_secs(83)));
+        assert_eq!(parse_timestamp("1:02:03"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_timestamp("1h2m3s"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_timestamp("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_timestamp("2m"), Some(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        for timestamp in ["", "  ", "1:2:3:4", "1::2", "1:", "abc", "1x", "1h2", "-5", "1:-5", "99999999999999999999"] {
+            assert_eq!(parse_timestamp(timestamp), None, "{}", timestamp);
+        }
+    }
+}